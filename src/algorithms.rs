@@ -1,14 +1,62 @@
+mod counter;
+pub mod external_sort;
+pub mod memo;
+
+pub use counter::Counter;
+use std::cmp::Ordering;
+use std::ops::Range;
+
+/// Finds the range of indices in the slice whose values equal `x`.
+/// If the slice is not sorted, the returned result is unspecified and meaningless.
+pub fn equal_range<T: Ord>(s: &[T], x: &T) -> Range<usize> {
+    let low = s.partition_point(|v| v < x);
+    let high = low + s[low..].partition_point(|v| v <= x);
+
+    low..high
+}
+
 /// Counts the amount of times `x` is present in the slice
 /// If the slice is not sorted, the returned result is unspecified and meaningless.
 pub fn binary_count<T: Ord>(s: &[T], x: &T) -> usize {
-    let low = s.partition_point(|v| v < x);
-    let high = s.partition_point(|v| v <= x);
+    equal_range(s, x).len()
+}
+
+/// Walks two sorted slices in a single linear pass, yielding every value present in both along
+/// with its number of occurrences in `a` and in `b`.
+/// If the slices are not sorted, the returned result is unspecified and meaningless.
+pub fn merge_join<'a, T: Ord>(
+    a: &'a [T],
+    b: &'a [T],
+) -> impl Iterator<Item = (&'a T, usize, usize)> {
+    let (mut i, mut j) = (0, 0);
+
+    std::iter::from_fn(move || loop {
+        let (x, y) = (a.get(i)?, b.get(j)?);
+
+        match x.cmp(y) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                let count_a = a[i..].iter().take_while(|v| *v == x).count();
+                let count_b = b[j..].iter().take_while(|v| *v == x).count();
+                i += count_a;
+                j += count_b;
+                return Some((x, count_a, count_b));
+            }
+        }
+    })
+}
 
-    high - low
+/// Counts the pairs of equal values between two sorted slices in a single linear pass.
+/// If the slices are not sorted, the returned result is unspecified and meaningless.
+pub fn count_matches<T: Ord>(a: &[T], b: &[T]) -> usize {
+    merge_join(a, b).map(|(_, ca, cb)| ca * cb).sum()
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::testing;
+
     #[test]
     fn test_binary_count() {
         let haystack = [2, 2, 3, 3, 4, 5, 5, 5, 5, 6, 7, 8, 9, 9, 9];
@@ -24,4 +72,40 @@ mod tests {
         assert_eq!(super::binary_count(&haystack, &9), 3);
         assert_eq!(super::binary_count(&haystack, &10), 0);
     }
+
+    #[test]
+    fn test_binary_count_matches_naive() {
+        testing::check_against(
+            1000,
+            (
+                testing::sorted_vecs(testing::ints(0u8..=20), 0..=30),
+                testing::ints(0u8..=21),
+            ),
+            |(haystack, x)| super::binary_count(haystack, x),
+            |(haystack, x)| haystack.iter().filter(|v| *v == x).count(),
+        );
+    }
+
+    #[test]
+    fn test_equal_range() {
+        let haystack = [2, 2, 3, 3, 4, 5, 5, 5, 5, 6];
+
+        assert_eq!(super::equal_range(&haystack, &1), 0..0);
+        assert_eq!(super::equal_range(&haystack, &3), 2..4);
+        assert_eq!(super::equal_range(&haystack, &5), 5..9);
+        assert_eq!(super::equal_range(&haystack, &7), 10..10);
+    }
+
+    #[test]
+    fn test_merge_join() {
+        let a = [1, 3, 3, 3, 4, 7, 9, 9];
+        let b = [2, 3, 3, 4, 4, 4, 9, 10];
+
+        assert_eq!(
+            super::merge_join(&a, &b).collect::<Vec<_>>(),
+            [(&3, 3, 2), (&4, 1, 3), (&9, 2, 1)]
+        );
+        assert_eq!(super::count_matches(&a, &b), 6 + 3 + 2);
+        assert_eq!(super::count_matches(&a, &[]), 0);
+    }
 }
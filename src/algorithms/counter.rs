@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A multiset counting how many times each value has been added.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Counter<T: Eq + Hash> {
+    counts: HashMap<T, usize>,
+}
+
+impl<T: Eq + Hash> Default for Counter<T> {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Eq + Hash> Counter<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, value: T) {
+        self.add_n(value, 1);
+    }
+
+    pub fn add_n(&mut self, value: T, n: usize) {
+        if n > 0 {
+            *self.counts.entry(value).or_default() += n;
+        }
+    }
+
+    /// Removes up to `n` occurrences of `value`, returning how many were removed.
+    pub fn remove_n(&mut self, value: &T, n: usize) -> usize {
+        let Some(count) = self.counts.get_mut(value) else {
+            return 0;
+        };
+
+        let removed = n.min(*count);
+        *count -= removed;
+        if *count == 0 {
+            self.counts.remove(value);
+        }
+        removed
+    }
+
+    /// The number of times `value` has been added.
+    pub fn get(&self, value: &T) -> usize {
+        self.counts.get(value).copied().unwrap_or_default()
+    }
+
+    /// The number of distinct values.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The number of values including duplicates.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.counts.iter().map(|(v, &c)| (v, c))
+    }
+
+    /// All distinct values ordered from most to least common.
+    /// The order of values with equal counts is unspecified.
+    pub fn most_common(&self) -> Vec<(&T, usize)> {
+        let mut result = self.iter().collect::<Vec<_>>();
+        result.sort_unstable_by_key(|&(_, c)| std::cmp::Reverse(c));
+        result
+    }
+
+    /// The values present in both counters, each with the smaller of its two counts.
+    pub fn intersection(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        self.iter()
+            .filter_map(|(v, c)| {
+                let count = c.min(other.get(v));
+                (count > 0).then(|| (v.clone(), count))
+            })
+            .collect()
+    }
+
+    /// The values present in either counter, each with the larger of its two counts.
+    pub fn union(&self, other: &Self) -> Self
+    where
+        T: Clone,
+    {
+        let mut result = self.clone();
+        for (v, c) in other.iter() {
+            let count = result.counts.entry(v.clone()).or_default();
+            *count = (*count).max(c);
+        }
+        result
+    }
+
+    pub fn into_map(self) -> HashMap<T, usize> {
+        self.counts
+    }
+}
+
+impl<T: Eq + Hash> Extend<T> for Counter<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|v| self.add(v));
+    }
+}
+
+impl<T: Eq + Hash> FromIterator<T> for Counter<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut counter = Self::new();
+        counter.extend(iter);
+        counter
+    }
+}
+
+impl<T: Eq + Hash> FromIterator<(T, usize)> for Counter<T> {
+    fn from_iter<I: IntoIterator<Item = (T, usize)>>(iter: I) -> Self {
+        let mut counter = Self::new();
+        iter.into_iter().for_each(|(v, n)| counter.add_n(v, n));
+        counter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Counter;
+
+    #[test]
+    fn test_counter() {
+        let mut counter = "abracadabra".chars().collect::<Counter<_>>();
+
+        assert_eq!(counter.get(&'a'), 5);
+        assert_eq!(counter.get(&'z'), 0);
+        assert_eq!(counter.len(), 5);
+        assert_eq!(counter.total(), 11);
+        assert_eq!(counter.most_common()[0], (&'a', 5));
+
+        assert_eq!(counter.remove_n(&'b', 5), 2);
+        assert_eq!(counter.get(&'b'), 0);
+        assert_eq!(counter.len(), 4);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = "aaabc".chars().collect::<Counter<_>>();
+        let b = "abbd".chars().collect::<Counter<_>>();
+
+        assert_eq!(
+            a.intersection(&b),
+            [('a', 1), ('b', 1)].into_iter().collect()
+        );
+        assert_eq!(
+            a.union(&b),
+            [('a', 3), ('b', 2), ('c', 1), ('d', 1)]
+                .into_iter()
+                .collect()
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Sorting of more values than fit in memory.
+//!
+//! Values are collected in a buffer of bounded size. Every time it fills up, it is sorted and
+//! spilled to a temporary file as a run, and the runs are merged back together when reading.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct ExternalSort {
+    buffer: Vec<u64>,
+    capacity: usize,
+    runs: Vec<Run>,
+}
+
+impl ExternalSort {
+    /// Sorts values while keeping at most `capacity` of them in memory at once.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity should be positive");
+        Self {
+            buffer: vec![],
+            capacity,
+            runs: vec![],
+        }
+    }
+
+    pub fn push(&mut self, value: u64) -> io::Result<()> {
+        self.buffer.push(value);
+        if self.buffer.len() >= self.capacity {
+            self.buffer.sort_unstable();
+            self.runs.push(Run::write(&self.buffer)?);
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    /// The number of runs spilled to disk so far.
+    pub fn runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Finishes collecting values, returning them in ascending order.
+    pub fn finish(mut self) -> io::Result<Sorted> {
+        self.buffer.sort_unstable();
+
+        let mut sources = vec![Source::Memory(self.buffer.into_iter())];
+        for run in self.runs {
+            sources.push(Source::File(run.read()?));
+        }
+
+        let mut heap = BinaryHeap::new();
+        for (i, source) in sources.iter_mut().enumerate() {
+            if let Some(value) = source.next()? {
+                heap.push(Reverse((value, i)));
+            }
+        }
+
+        Ok(Sorted { sources, heap })
+    }
+}
+
+/// A sorted run spilled to a temporary file, which is removed again when dropped.
+struct Run {
+    path: PathBuf,
+    file: File,
+}
+
+impl Run {
+    fn write(values: &[u64]) -> io::Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("aoc2024-sort-{}-{id}", std::process::id()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let run = Self { path, file };
+
+        let mut writer = BufWriter::new(&run.file);
+        for value in values {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.flush()?;
+        drop(writer);
+
+        Ok(run)
+    }
+
+    fn read(mut self) -> io::Result<RunReader> {
+        self.file.rewind()?;
+        let reader = BufReader::new(self.file.try_clone()?);
+        Ok(RunReader { _run: self, reader })
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+struct RunReader {
+    _run: Run,
+    reader: BufReader<File>,
+}
+
+enum Source {
+    Memory(std::vec::IntoIter<u64>),
+    File(RunReader),
+}
+
+impl Source {
+    fn next(&mut self) -> io::Result<Option<u64>> {
+        match self {
+            Source::Memory(values) => Ok(values.next()),
+            Source::File(run) => {
+                let mut bytes = [0; 8];
+                match run.reader.read_exact(&mut bytes) {
+                    Ok(()) => Ok(Some(u64::from_le_bytes(bytes))),
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+}
+
+/// The values of an [ExternalSort] in ascending order, merged from all runs.
+pub struct Sorted {
+    sources: Vec<Source>,
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+}
+
+impl Iterator for Sorted {
+    type Item = io::Result<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((value, i)) = self.heap.pop()?;
+
+        match self.sources[i].next() {
+            Ok(Some(next)) => self.heap.push(Reverse((next, i))),
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+
+        Some(Ok(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExternalSort;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_external_sort() {
+        let mut rng = Rng::new(5);
+        let mut values = (0..1000).map(|_| rng.range(0..500)).collect::<Vec<u64>>();
+
+        let mut sort = ExternalSort::new(64);
+        for &v in &values {
+            sort.push(v).unwrap();
+        }
+        assert_eq!(sort.runs(), 15);
+
+        let sorted = sort.finish().unwrap().collect::<Result<Vec<_>, _>>();
+        values.sort_unstable();
+        assert_eq!(sorted.unwrap(), values);
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(ExternalSort::new(1).finish().unwrap().count(), 0);
+    }
+}
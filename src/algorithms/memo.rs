@@ -0,0 +1,218 @@
+//! Memoization for recursive searches.
+//!
+//! A [Memo] only holds the cache; the recursive function receives it as an argument and wraps its
+//! body in [Memo::get_or_insert_with], passing the memo on to the recursive calls:
+//!
+//! ```
+//! use aoc2024::algorithms::memo::Memo;
+//!
+//! fn fibonacci(memo: &mut Memo<u64, u64>, n: u64) -> u64 {
+//!     memo.get_or_insert_with(n, |memo| match n {
+//!         0 | 1 => n,
+//!         n => fibonacci(memo, n - 1) + fibonacci(memo, n - 2),
+//!     })
+//! }
+//!
+//! assert_eq!(fibonacci(&mut Memo::new(), 90), 2880067194370816120);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+
+/// A map that can be used as the cache of a [Memo].
+pub trait Cache<K, V> {
+    fn lookup(&self, key: &K) -> Option<V>;
+
+    fn store(&mut self, key: K, value: V);
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Eq + Hash, V: Clone, S: BuildHasher> Cache<K, V> for HashMap<K, V, S> {
+    fn lookup(&self, key: &K) -> Option<V> {
+        self.get(key).cloned()
+    }
+
+    fn store(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K: Ord, V: Clone> Cache<K, V> for BTreeMap<K, V> {
+    fn lookup(&self, key: &K) -> Option<V> {
+        self.get(key).cloned()
+    }
+
+    fn store(&mut self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+}
+
+/// A cache of results of a recursive function, keyed on its arguments.
+#[derive(Debug, Clone)]
+pub struct Memo<K, V, C = HashMap<K, V>> {
+    cache: C,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K, V, C: Default> Default for Memo<K, V, C> {
+    fn default() -> Self {
+        Self::with_cache(C::default())
+    }
+}
+
+impl<K, V, C: Default> Memo<K, V, C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, C> Memo<K, V, C> {
+    pub fn with_cache(cache: C) -> Self {
+        Self {
+            cache,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn into_cache(self) -> C {
+        self.cache
+    }
+}
+
+impl<K: Clone, V: Clone, C: Cache<K, V>> Memo<K, V, C> {
+    /// Returns the cached value for `key`, or computes and caches it with `f`.
+    /// `f` receives the memo so that it can make memoized recursive calls.
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce(&mut Self) -> V) -> V {
+        if let Some(value) = self.cache.lookup(&key) {
+            return value;
+        }
+
+        let value = f(self);
+        self.cache.store(key, value.clone());
+        value
+    }
+
+    /// The number of cached results.
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// Searches for a way to combine `items` in order into exactly `target`, starting from `initial`
+/// and applying one of `ops` at every step.
+///
+/// `is_monotone` tells whether no operation with an item can decrease the accumulated value, such
+/// as a positive operand for additions and multiplications. Once all remaining items are monotone,
+/// any branch that exceeds `target` is pruned immediately. Operations return [None] to prune a
+/// branch outright, such as on overflow. Dead ends are memoized on the item index and accumulated
+/// value.
+pub fn monotone_search<T: Copy + Ord + Hash>(
+    initial: T,
+    items: &[T],
+    target: T,
+    ops: &[fn(T, T) -> Option<T>],
+    is_monotone: impl Fn(T) -> bool,
+) -> bool {
+    struct Search<'a, T> {
+        items: &'a [T],
+        target: T,
+        ops: &'a [fn(T, T) -> Option<T>],
+        /// The index from which all items are monotone.
+        monotone_from: usize,
+    }
+
+    fn go<T: Copy + Ord + Hash>(
+        memo: &mut Memo<(usize, T), bool>,
+        search: &Search<T>,
+        value: T,
+        index: usize,
+    ) -> bool {
+        if index >= search.monotone_from && value > search.target {
+            return false;
+        }
+
+        let Some(&next) = search.items.get(index) else {
+            return value == search.target;
+        };
+
+        memo.get_or_insert_with((index, value), |memo| {
+            search
+                .ops
+                .iter()
+                .filter_map(|op| op(value, next))
+                .any(|v| go(memo, search, v, index + 1))
+        })
+    }
+
+    let search = Search {
+        items,
+        target,
+        ops,
+        monotone_from: items
+            .iter()
+            .rposition(|&item| !is_monotone(item))
+            .map_or(0, |i| i + 1),
+    };
+    go(&mut Memo::new(), &search, initial, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{monotone_search, Memo};
+    use std::collections::BTreeMap;
+
+    type PartitionsMemo = Memo<(u64, u64), u64, BTreeMap<(u64, u64), u64>>;
+
+    fn partitions(memo: &mut PartitionsMemo, n: u64, max: u64) -> u64 {
+        memo.get_or_insert_with((n, max), |memo| match (n, max) {
+            (0, _) => 1,
+            (_, 0) => 0,
+            (n, max) => (1..=max.min(n)).map(|k| partitions(memo, n - k, k)).sum(),
+        })
+    }
+
+    #[test]
+    fn test_memo() {
+        let mut memo = Memo::new();
+        assert_eq!(partitions(&mut memo, 100, 100), 190569292);
+
+        let cached = memo.len();
+        assert_eq!(partitions(&mut memo, 100, 100), 190569292);
+        assert_eq!(memo.len(), cached);
+    }
+
+    #[test]
+    fn test_monotone_search() {
+        let ops: &[fn(u64, u64) -> Option<u64>] = &[u64::checked_add, u64::checked_mul];
+        let positive = |n| n > 0;
+
+        assert!(monotone_search(81, &[40, 27], 3267, ops, positive));
+        assert!(monotone_search(11, &[6, 16, 20], 292, ops, positive));
+        assert!(!monotone_search(17, &[5], 83, ops, positive));
+        assert!(monotone_search(5, &[], 5, ops, positive));
+        assert!(!monotone_search(u64::MAX, &[2], u64::MAX, ops, positive));
+
+        // Multiplying by zero brings an overshooting value back down.
+        assert!(monotone_search(7, &[0, 6], 6, ops, positive));
+        assert!(monotone_search(7, &[3, 0], 0, ops, positive));
+        assert!(!monotone_search(7, &[0, 6], 5, ops, positive));
+    }
+}
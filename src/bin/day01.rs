@@ -1,6 +1,12 @@
-use aoc2024::{algorithms, parsing};
-use std::process::Termination;
+use aoc2024::algorithms::external_sort::ExternalSort;
+use aoc2024::algorithms::Counter;
+use aoc2024::parsing::{Fields, ParseError};
+use aoc2024::{algorithms, cli, parsing};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::process::{ExitCode, Termination};
 use std::str::FromStr;
+use std::time::Instant;
 
 #[derive(Debug)]
 struct Line {
@@ -15,44 +21,106 @@ impl Line {
 }
 
 impl FromStr for Line {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut split = s.split_ascii_whitespace();
-        Ok(Line {
-            left: split.next().unwrap().parse().unwrap(),
-            right: split.next().unwrap().parse().unwrap(),
-        })
+        let mut fields = Fields::whitespace(s);
+        let line = Line {
+            left: fields.parse("left location ID")?,
+            right: fields.parse("right location ID")?,
+        };
+        fields.finish()?;
+        Ok(line)
     }
 }
 
-fn solver(input: &str) -> (usize, usize) {
+fn sorted_lists(input: &str) -> (Vec<usize>, Vec<usize>) {
     let (mut left_list, mut right_list): (Vec<_>, Vec<_>) =
         parsing::parse_list(input).map(Line::into_tuple).unzip();
 
     left_list.sort();
     right_list.sort();
 
-    let part1 = left_list
+    (left_list, right_list)
+}
+
+fn part1(input: &str) -> usize {
+    let (left_list, right_list) = sorted_lists(input);
+
+    left_list
         .iter()
         .zip(right_list.iter())
         .map(|(l, r)| l.abs_diff(*r))
-        .sum::<usize>();
+        .sum::<usize>()
+}
 
-    let part2 = left_list
-        .into_iter()
-        .map(|v| v * algorithms::binary_count(&right_list, &v))
-        .sum::<usize>();
+fn part2(input: &str) -> usize {
+    let (left_list, right_list) = sorted_lists(input);
 
-    (part1, part2)
+    algorithms::merge_join(&left_list, &right_list)
+        .map(|(v, left_count, right_count)| v * left_count * right_count)
+        .sum::<usize>()
 }
 
-fn main() -> impl Termination {
-    aoc2024::execute_day(1, solver)
+/// Solves an input of any size in a single pass, keeping at most `capacity` location IDs per list
+/// in memory. The distance is computed by sorting the lists externally, the similarity score from
+/// the number of occurrences of every ID.
+fn solve_stream(reader: impl BufRead, capacity: usize) -> io::Result<(usize, usize)> {
+    let mut left_list = ExternalSort::new(capacity);
+    let mut right_list = ExternalSort::new(capacity);
+    let mut left_counts = Counter::new();
+    let mut right_counts = Counter::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let Line { left, right } = line.parse().map_err(|e: ParseError| {
+            io::Error::new(io::ErrorKind::InvalidData, e.relative_to(i + 1))
+        })?;
+
+        left_list.push(left as u64)?;
+        right_list.push(right as u64)?;
+        left_counts.add(left);
+        right_counts.add(right);
+    }
+
+    let mut part1 = 0;
+    for (l, r) in left_list.finish()?.zip(right_list.finish()?) {
+        part1 += l?.abs_diff(r?) as usize;
+    }
+
+    let part2 = left_counts
+        .iter()
+        .map(|(&v, count)| v * count * right_counts.get(&v))
+        .sum();
+
+    Ok((part1, part2))
+}
+
+fn main() -> ExitCode {
+    let Some(path) = cli::option("--stream") else {
+        return aoc2024::execute_day(1, part1, part2).report();
+    };
+    let capacity = cli::parsed_option("--capacity").unwrap_or(1 << 24);
+
+    let start = Instant::now();
+    let (part1, part2) = match path.as_str() {
+        "-" => solve_stream(io::stdin().lock(), capacity),
+        path => File::open(path).and_then(|f| solve_stream(BufReader::new(f), capacity)),
+    }
+    .unwrap_or_else(|e| panic!("failed to solve {path}: {e}"));
+
+    println!("Part 1: {part1}");
+    println!("Part 2: {part2}");
+    println!("Runtime: {:?}", start.elapsed());
+
+    ExitCode::SUCCESS
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::{part1, part2, solve_stream};
+    use aoc2024::generate::{self, Options};
+
     const EXAMPLE_INPUT: &str = "3   4
 4   3
 2   5
@@ -62,6 +130,62 @@ mod tests {
 
     #[test]
     fn test() {
-        aoc2024::test_day(super::solver, EXAMPLE_INPUT, (11, Some(31)));
+        aoc2024::test_day(part1, part2, EXAMPLE_INPUT, (11, Some(31)));
+    }
+
+    #[test]
+    fn test_stream() {
+        assert_eq!(solve_stream(EXAMPLE_INPUT.as_bytes(), 2).unwrap(), (11, 31));
+        assert!(solve_stream("1 2\n3".as_bytes(), 2).is_err());
+
+        let input = generate::input(1, 3, Options::default());
+        assert_eq!(
+            solve_stream(input.as_bytes(), 100).unwrap(),
+            (part1(&input), part2(&input))
+        );
+    }
+
+    /// Counts the occurrences of every left ID by scanning the whole right list.
+    fn reference(input: &str) -> (usize, usize) {
+        let (mut left, mut right): (Vec<usize>, Vec<usize>) = input
+            .lines()
+            .map(|l| {
+                let mut ids = l.split_whitespace().map(|v| v.parse::<usize>().unwrap());
+                (ids.next().unwrap(), ids.next().unwrap())
+            })
+            .unzip();
+
+        let part2 = left
+            .iter()
+            .map(|l| l * right.iter().filter(|r| *r == l).count())
+            .sum();
+
+        left.sort();
+        right.sort();
+        let part1 = left.iter().zip(&right).map(|(l, r)| l.abs_diff(*r)).sum();
+
+        (part1, part2)
+    }
+
+    #[test]
+    fn test_differential() {
+        let options = Options {
+            size: 300,
+            difficulty: 0.8,
+        };
+        aoc2024::differential_test_day(
+            1,
+            |input| (part1(input), part2(input)),
+            reference,
+            options,
+            20,
+        );
+        aoc2024::differential_test_day(
+            1,
+            |input| solve_stream(input.as_bytes(), 16).unwrap(),
+            reference,
+            options,
+            20,
+        );
     }
 }
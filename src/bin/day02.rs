@@ -1,63 +1,272 @@
-use std::process::Termination;
+use aoc2024::cli;
+use aoc2024::parsing::{Fields, ParseError};
+use std::process::{ExitCode, Termination};
 use std::str::FromStr;
 
 struct Report(Vec<usize>);
 
 impl FromStr for Report {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Report(
-            s.split_whitespace().map(|d| d.parse().unwrap()).collect(),
-        ))
+        Ok(Report(Fields::whitespace(s).parse_rest("level")?))
     }
 }
 
-fn solver(input: &str) -> (usize, usize) {
-    let reports = aoc2024::parsing::parse_list::<Report>(input).collect::<Vec<_>>();
-    let part1 = reports.iter().filter(|r| is_safe(r)).count();
-    let part2 = reports.into_iter().filter(is_safe_dampened).count();
-    (part1, part2)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Direction {
+    /// Either direction, as long as it matches the first step between different levels.
+    Any,
+    Increasing,
+    Decreasing,
 }
 
-fn is_safe(report: &Report) -> bool {
-    let values = &report.0;
+impl FromStr for Direction {
+    type Err = String;
 
-    if values.len() < 2 {
-        return true;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(Direction::Any),
+            "increasing" => Ok(Direction::Increasing),
+            "decreasing" => Ok(Direction::Decreasing),
+            _ => Err("expected \"any\", \"increasing\" or \"decreasing\"".to_owned()),
+        }
     }
+}
 
-    let increasing = values[1] > values[0];
+/// The rules a report must follow to be safe.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct SafetyPolicy {
+    min_step: usize,
+    max_step: usize,
+    direction: Direction,
+    /// How many levels the Problem Dampener may remove.
+    tolerance: usize,
+}
 
-    values.windows(2).all(|v| {
-        v[0] != v[1]
-            && v[0].abs_diff(v[1]) <= 3
-            && if increasing { v[1] > v[0] } else { v[1] < v[0] }
-    })
+impl SafetyPolicy {
+    const PART1: SafetyPolicy = SafetyPolicy {
+        min_step: 1,
+        max_step: 3,
+        direction: Direction::Any,
+        tolerance: 0,
+    };
+
+    const PART2: SafetyPolicy = SafetyPolicy {
+        tolerance: 1,
+        ..Self::PART1
+    };
+
+    /// Reads the policy from the command line, using the rules of part 2 for anything not given.
+    fn from_args() -> Self {
+        let defaults = Self::PART2;
+        Self {
+            min_step: cli::parsed_option("--min-step").unwrap_or(defaults.min_step),
+            max_step: cli::parsed_option("--max-step").unwrap_or(defaults.max_step),
+            direction: cli::parsed_option("--direction").unwrap_or(defaults.direction),
+            tolerance: cli::parsed_option("--tolerance").unwrap_or(defaults.tolerance),
+        }
+    }
+}
+
+fn part1(input: &str) -> usize {
+    aoc2024::parsing::parse_list::<Report>(input)
+        .filter(|r| is_safe(r, &SafetyPolicy::PART1))
+        .count()
+}
+
+fn part2(input: &str) -> usize {
+    aoc2024::parsing::parse_list::<Report>(input)
+        .filter(|r| is_safe_dampened(r, &SafetyPolicy::PART2))
+        .count()
+}
+
+/// Why a pair of adjacent levels makes a report unsafe.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Reason {
+    /// The levels change in the opposite direction of the first step between different levels.
+    DirectionChange,
+    /// The levels change in the opposite direction of the one the policy requires.
+    WrongDirection,
+    StepTooSmall,
+    StepTooLarge,
+    Equal,
+}
+
+/// A level removed by the Problem Dampener, with the reason the report was unsafe before.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Removal {
+    index: usize,
+    reason: Reason,
+}
+
+/// The first unsafe pair of adjacent levels, given as positions within the kept levels.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Violation {
+    /// The position of the first level of the pair.
+    position: usize,
+    reason: Reason,
+    /// The position of the first level of the step that set the direction.
+    direction_set_at: usize,
 }
 
-fn is_safe_dampened(report: &Report) -> bool {
-    if is_safe(report) {
-        return true;
+impl Violation {
+    /// The levels of which at least one must be removed to resolve the violation.
+    ///
+    /// Normally one of the levels of the pair must go. If the direction changes, both may be kept
+    /// only if the direction of the report is reversed, which requires removing a level of the step
+    /// that set it.
+    fn candidates(&self) -> Vec<usize> {
+        let mut candidates = vec![self.position, self.position + 1];
+        if self.reason == Reason::DirectionChange {
+            candidates.extend([self.direction_set_at, self.direction_set_at + 1]);
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
     }
+}
+
+/// Finds the first unsafe pair of adjacent levels among the `kept` indices of `levels`.
+fn first_violation(levels: &[usize], kept: &[usize], policy: &SafetyPolicy) -> Option<Violation> {
+    let mut increasing = match policy.direction {
+        Direction::Any => None,
+        Direction::Increasing => Some((true, 0)),
+        Direction::Decreasing => Some((false, 0)),
+    };
+
+    kept.windows(2).enumerate().find_map(|(i, pair)| {
+        let (a, b) = (levels[pair[0]], levels[pair[1]]);
+        let step = a.abs_diff(b);
+
+        let reason = if step < policy.min_step {
+            if a == b {
+                Reason::Equal
+            } else {
+                Reason::StepTooSmall
+            }
+        } else if step > policy.max_step {
+            Reason::StepTooLarge
+        } else {
+            match increasing {
+                _ if a == b => return None,
+                None => {
+                    increasing = Some((b > a, i));
+                    return None;
+                }
+                Some((increasing, _)) if (b > a) == increasing => return None,
+                Some(_) if policy.direction == Direction::Any => Reason::DirectionChange,
+                Some(_) => Reason::WrongDirection,
+            }
+        };
+
+        Some(Violation {
+            position: i,
+            reason,
+            direction_set_at: increasing.map_or(0, |(_, at)| at),
+        })
+    })
+}
+
+/// Whether the report follows the policy without removing any levels.
+fn is_safe(report: &Report, policy: &SafetyPolicy) -> bool {
+    let kept = (0..report.0.len()).collect::<Vec<_>>();
+    first_violation(&report.0, &kept, policy).is_none()
+}
+
+/// Whether the report follows the policy after removing at most `policy.tolerance` levels.
+fn is_safe_dampened(report: &Report, policy: &SafetyPolicy) -> bool {
+    dampen(report, policy).is_some()
+}
+
+/// Finds the fewest levels, at most `policy.tolerance`, to remove to make the report safe.
+/// Returns [None] if more levels would have to be removed.
+fn dampen(report: &Report, policy: &SafetyPolicy) -> Option<Vec<Removal>> {
+    let kept = (0..report.0.len()).collect::<Vec<_>>();
 
-    let mut new_report = Report(Vec::with_capacity(report.0.len() - 1));
+    (0..=policy.tolerance).find_map(|budget| dampen_within(&report.0, &kept, policy, budget))
+}
+
+/// Each step tries at most four removals, so this takes linear time for a fixed budget.
+fn dampen_within(
+    levels: &[usize],
+    kept: &[usize],
+    policy: &SafetyPolicy,
+    budget: usize,
+) -> Option<Vec<Removal>> {
+    let Some(violation) = first_violation(levels, kept, policy) else {
+        return Some(vec![]);
+    };
+
+    if budget == 0 {
+        return None;
+    }
 
-    (0..report.0.len()).any(|i| {
-        new_report.0.clear();
-        new_report.0.extend(&report.0[..i]);
-        new_report.0.extend(&report.0[i + 1..]);
-        is_safe(&new_report)
+    violation.candidates().into_iter().find_map(|candidate| {
+        let remaining = [&kept[..candidate], &kept[candidate + 1..]].concat();
+        let mut removals = dampen_within(levels, &remaining, policy, budget - 1)?;
+        removals.insert(
+            0,
+            Removal {
+                index: kept[candidate],
+                reason: violation.reason,
+            },
+        );
+        Some(removals)
     })
 }
 
-fn main() -> impl Termination {
-    aoc2024::execute_day(2, solver)
+/// Describes for every report whether it follows the policy and, if so, which levels to remove.
+fn evaluate(input: &str, policy: &SafetyPolicy) -> Vec<String> {
+    aoc2024::parsing::parse_list::<Report>(input)
+        .enumerate()
+        .map(|(i, report)| {
+            let verdict = match dampen(&report, policy) {
+                Some(removals) if removals.is_empty() => "safe".to_owned(),
+                Some(removals) => {
+                    let removed = removals
+                        .iter()
+                        .map(|r| format!("level {} ({:?})", r.index + 1, r.reason))
+                        .collect::<Vec<_>>();
+                    format!("safe after removing {}", removed.join(", "))
+                }
+                None => {
+                    let kept = (0..report.0.len()).collect::<Vec<_>>();
+                    let violation = first_violation(&report.0, &kept, policy).unwrap();
+                    format!(
+                        "unsafe: {:?} at level {}",
+                        violation.reason,
+                        violation.position + 2
+                    )
+                }
+            };
+            format!("line {}: {verdict}", i + 1)
+        })
+        .collect()
+}
+
+fn main() -> ExitCode {
+    let Some(path) = cli::option("--reports") else {
+        return aoc2024::execute_day(2, part1, part2).report();
+    };
+
+    let input = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("failed to read reports from {path}: {e}"));
+    for line in evaluate(&input, &SafetyPolicy::from_args()) {
+        println!("{line}");
+    }
+
+    ExitCode::SUCCESS
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{is_safe, is_safe_dampened, solver, Report};
+    use crate::{
+        dampen, evaluate, is_safe, is_safe_dampened, part1, part2, Direction, Reason, Removal,
+        Report, SafetyPolicy,
+    };
+    use aoc2024::generate::Options;
+    use aoc2024::random::Rng;
 
     const EXAMPLE_INPUT: &str = "7 6 4 2 1
 1 2 7 8 9
@@ -68,26 +277,199 @@ mod tests {
 
     #[test]
     fn test_day() {
-        aoc2024::test_day(solver, EXAMPLE_INPUT, (2, Some(4)));
+        aoc2024::test_day(part1, part2, EXAMPLE_INPUT, (2, Some(4)));
     }
 
     #[test]
     fn test_is_safe() {
-        assert!(is_safe(&Report(vec![7, 6, 4, 2, 1])));
-        assert!(!is_safe(&Report(vec![1, 2, 7, 8, 9])));
-        assert!(!is_safe(&Report(vec![9, 7, 6, 2, 1])));
-        assert!(!is_safe(&Report(vec![1, 3, 2, 4, 5])));
-        assert!(!is_safe(&Report(vec![8, 6, 4, 4, 1])));
-        assert!(is_safe(&Report(vec![1, 3, 6, 7, 9])));
+        let policy = &SafetyPolicy::PART1;
+        assert!(is_safe(&Report(vec![7, 6, 4, 2, 1]), policy));
+        assert!(!is_safe(&Report(vec![1, 2, 7, 8, 9]), policy));
+        assert!(!is_safe(&Report(vec![9, 7, 6, 2, 1]), policy));
+        assert!(!is_safe(&Report(vec![1, 3, 2, 4, 5]), policy));
+        assert!(!is_safe(&Report(vec![8, 6, 4, 4, 1]), policy));
+        assert!(is_safe(&Report(vec![1, 3, 6, 7, 9]), policy));
     }
 
     #[test]
     fn test_is_safe_dampened() {
-        assert!(is_safe_dampened(&Report(vec![7, 6, 4, 2, 1])));
-        assert!(!is_safe_dampened(&Report(vec![1, 2, 7, 8, 9])));
-        assert!(!is_safe_dampened(&Report(vec![9, 7, 6, 2, 1])));
-        assert!(is_safe_dampened(&Report(vec![1, 3, 2, 4, 5])));
-        assert!(is_safe_dampened(&Report(vec![8, 6, 4, 4, 1])));
-        assert!(is_safe_dampened(&Report(vec![1, 3, 6, 7, 9])));
+        let policy = &SafetyPolicy::PART2;
+        assert!(is_safe_dampened(&Report(vec![7, 6, 4, 2, 1]), policy));
+        assert!(!is_safe_dampened(&Report(vec![1, 2, 7, 8, 9]), policy));
+        assert!(!is_safe_dampened(&Report(vec![9, 7, 6, 2, 1]), policy));
+        assert!(is_safe_dampened(&Report(vec![1, 3, 2, 4, 5]), policy));
+        assert!(is_safe_dampened(&Report(vec![8, 6, 4, 4, 1]), policy));
+        assert!(is_safe_dampened(&Report(vec![1, 3, 6, 7, 9]), policy));
+    }
+
+    #[test]
+    fn test_dampen() {
+        let removal = |index, reason| Removal { index, reason };
+        let policy = &SafetyPolicy::PART2;
+
+        assert_eq!(dampen(&Report(vec![7, 6, 4, 2, 1]), policy), Some(vec![]));
+        assert_eq!(
+            dampen(&Report(vec![1, 3, 2, 4, 5]), policy),
+            Some(vec![removal(1, Reason::DirectionChange)])
+        );
+        assert_eq!(
+            dampen(&Report(vec![8, 6, 4, 4, 1]), policy),
+            Some(vec![removal(2, Reason::Equal)])
+        );
+        assert_eq!(
+            dampen(&Report(vec![3, 2, 4, 5, 6]), policy),
+            Some(vec![removal(0, Reason::DirectionChange)])
+        );
+        assert_eq!(dampen(&Report(vec![1, 2, 7, 8, 9]), policy), None);
+        assert_eq!(
+            dampen(
+                &Report(vec![1, 2, 7, 8, 9]),
+                &SafetyPolicy {
+                    tolerance: 2,
+                    ..*policy
+                }
+            ),
+            Some(vec![
+                removal(1, Reason::StepTooLarge),
+                removal(0, Reason::StepTooLarge)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_policy() {
+        let increasing = SafetyPolicy {
+            min_step: 0,
+            max_step: 5,
+            direction: Direction::Increasing,
+            tolerance: 1,
+        };
+
+        assert!(is_safe(&Report(vec![1, 1, 6, 8]), &increasing));
+        assert!(!is_safe(&Report(vec![8, 6, 1, 1]), &increasing));
+        assert_eq!(dampen(&Report(vec![1, 2, 2, 1, 0]), &increasing), None);
+        assert_eq!(
+            dampen(
+                &Report(vec![1, 2, 2, 1, 0]),
+                &SafetyPolicy {
+                    direction: Direction::Any,
+                    ..increasing
+                }
+            ),
+            Some(vec![Removal {
+                index: 0,
+                reason: Reason::DirectionChange
+            }])
+        );
+        assert_eq!(
+            evaluate("1 3 2 9\n1 2 3\n2 1 2", &increasing),
+            [
+                "line 1: unsafe: WrongDirection at level 3",
+                "line 2: safe",
+                "line 3: safe after removing level 1 (WrongDirection)",
+            ]
+        );
+    }
+
+    fn is_safe_naive(levels: &[usize], policy: &SafetyPolicy) -> bool {
+        let steps = levels
+            .windows(2)
+            .filter(|v| v[0] != v[1])
+            .map(|v| v[1] > v[0])
+            .collect::<Vec<_>>();
+        let direction_ok = match policy.direction {
+            Direction::Any => steps.iter().all(|&s| s == steps[0]),
+            Direction::Increasing => steps.iter().all(|&s| s),
+            Direction::Decreasing => steps.iter().all(|&s| !s),
+        };
+
+        direction_ok
+            && levels
+                .windows(2)
+                .all(|v| (policy.min_step..=policy.max_step).contains(&v[0].abs_diff(v[1])))
+    }
+
+    /// Removes every combination of up to `policy.tolerance` levels.
+    fn dampen_naive(levels: &[usize], policy: &SafetyPolicy) -> Option<usize> {
+        (0..=policy.tolerance).find(|&k| removals_naive(levels, policy, k))
+    }
+
+    fn removals_naive(levels: &[usize], policy: &SafetyPolicy, k: usize) -> bool {
+        if k == 0 {
+            return is_safe_naive(levels, policy);
+        }
+        (0..levels.len())
+            .any(|i| removals_naive(&[&levels[..i], &levels[i + 1..]].concat(), policy, k - 1))
+    }
+
+    #[test]
+    fn test_dampen_matches_naive() {
+        let mut rng = Rng::new(2);
+        let directions = [Direction::Any, Direction::Increasing, Direction::Decreasing];
+
+        for _ in 0..3000 {
+            let levels = (0..rng.range(0..=8))
+                .map(|_| rng.range(1..=12))
+                .collect::<Vec<usize>>();
+            let min_step = rng.range(0..=2);
+            let policy = SafetyPolicy {
+                min_step,
+                max_step: rng.range(min_step..=5),
+                direction: *rng.choose(&directions),
+                tolerance: rng.range(0..=2),
+            };
+
+            let removals = dampen(&Report(levels.clone()), &policy);
+            assert_eq!(
+                removals.as_ref().map(Vec::len),
+                dampen_naive(&levels, &policy),
+                "removals differ for {levels:?} with {policy:?}"
+            );
+
+            if let Some(removals) = removals {
+                let kept = (0..levels.len())
+                    .filter(|i| removals.iter().all(|r| r.index != *i))
+                    .map(|i| levels[i])
+                    .collect::<Vec<_>>();
+                assert!(
+                    is_safe_naive(&kept, &policy),
+                    "unsafe after removing {removals:?}"
+                );
+            }
+        }
+    }
+
+    /// Tries removing every level in turn.
+    fn reference(input: &str) -> (usize, usize) {
+        let safe = |levels: &[i64]| {
+            levels.windows(2).all(|w| (1..=3).contains(&(w[1] - w[0])))
+                || levels.windows(2).all(|w| (1..=3).contains(&(w[0] - w[1])))
+        };
+
+        input.lines().fold((0, 0), |(p1, p2), line| {
+            let levels = line
+                .split_whitespace()
+                .map(|l| l.parse().unwrap())
+                .collect::<Vec<i64>>();
+            let dampened =
+                (0..levels.len()).any(|i| safe(&[&levels[..i], &levels[i + 1..]].concat()));
+
+            (p1 + safe(&levels) as usize, p2 + dampened as usize)
+        })
+    }
+
+    #[test]
+    fn test_differential() {
+        let options = Options {
+            size: 200,
+            difficulty: 0.7,
+        };
+        aoc2024::differential_test_day(
+            2,
+            |input| (part1(input), part2(input)),
+            reference,
+            options,
+            20,
+        );
     }
 }
@@ -1,148 +1,242 @@
+use aoc2024::cli;
+use aoc2024::parsing::combinators::{literal, number, seq, Parser};
+use aoc2024::vm::{Machine, Operands, Step};
 use std::process::Termination;
 
 const DO_INSTRUCTION: &str = "do()";
 
 const DO_NOT_INSTRUCTION: &str = "don't()";
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-struct MulData {
-    left: usize,
-    right: usize,
+/// Matches `mul(X,Y)`, where `X` and `Y` are unsigned numbers of one to three digits.
+fn parse_mul<'a>() -> impl Parser<'a, Output = Operands> {
+    seq((
+        literal("mul("),
+        number(1, 3),
+        literal(","),
+        number(1, 3),
+        literal(")"),
+    ))
+    .map(|(_, left, _, right, _)| vec![left, right])
 }
 
-impl MulData {
-    fn compute(self) -> usize {
-        self.left * self.right
-    }
+/// A machine that only knows `mul`, as in part 1.
+fn mul_machine<'a>() -> Machine<'a> {
+    Machine::new().opcode("mul", parse_mul(), |state, operands| {
+        if state.enabled {
+            state.accumulator += operands[0] * operands[1];
+        }
+    })
 }
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone)]
-enum Instruction {
-    Mul(MulData),
-    Do,
-    DoNot,
+/// A machine that also knows the conditional instructions of part 2.
+fn conditional_machine<'a>() -> Machine<'a> {
+    mul_machine()
+        .opcode("do", literal(DO_INSTRUCTION).map(|_| vec![]), |state, _| {
+            state.enabled = true
+        })
+        .opcode(
+            "don't",
+            literal(DO_NOT_INSTRUCTION).map(|_| vec![]),
+            |state, _| state.enabled = false,
+        )
 }
 
-impl Instruction {
-    fn new_mul(left: usize, right: usize) -> Self {
-        Self::Mul(MulData { left, right })
-    }
+fn part1(input: &str) -> i64 {
+    mul_machine().run(input).accumulator
+}
 
-    fn mul(&self) -> Option<MulData> {
-        match self {
-            Instruction::Mul(m) => Some(*m),
-            _ => None,
-        }
-    }
+fn part2(input: &str) -> i64 {
+    conditional_machine().run(input).accumulator
 }
 
-fn parse_mul(input: &str) -> Option<(Instruction, usize)> {
-    let (left, right) = input.strip_prefix("mul(")?.split_once(',')?;
+fn format_step(step: &Step) -> String {
+    let operands = step
+        .operands
+        .iter()
+        .map(|o| o.to_string())
+        .collect::<Vec<_>>();
+
+    format!(
+        "{:>6}: {}({}) enabled={} accumulator={}",
+        step.start,
+        step.opcode,
+        operands.join(","),
+        step.state.enabled,
+        step.state.accumulator
+    )
+}
 
-    let left_len = left.len();
-    let left = left.parse::<usize>().ok()?;
-    let right_len = right.find(")")?;
-    let right = right[..right_len].parse::<usize>().ok()?;
+fn main() -> impl Termination {
+    let trace = cli::flag("--trace");
 
-    Some((
-        Instruction::new_mul(left, right),
-        "mul(".len() + left_len + right_len + 2,
-    ))
-}
+    let result = aoc2024::execute_day(3, part1, part2);
 
-fn parse(input: &str) -> Vec<Instruction> {
-    let mut result = vec![];
-    let mut remaining = input;
-
-    while !remaining.is_empty() {
-        if remaining.starts_with(DO_INSTRUCTION) {
-            result.push(Instruction::Do);
-            remaining = &remaining[DO_INSTRUCTION.len()..];
-        } else if remaining.starts_with(DO_NOT_INSTRUCTION) {
-            result.push(Instruction::DoNot);
-            remaining = &remaining[DO_NOT_INSTRUCTION.len()..];
-        } else if let Some((mul, consumed)) = parse_mul(remaining) {
-            remaining = &remaining[consumed..];
-            result.push(mul);
-        } else {
-            remaining = &remaining[1..];
+    if trace {
+        let input = aoc2024::day_input(3);
+        for step in conditional_machine().trace(&input) {
+            println!("{}", format_step(&step));
         }
     }
 
     result
 }
 
-fn solver(input: &str) -> (usize, usize) {
-    let instructions = parse(input);
-
-    let part1 = instructions
-        .iter()
-        .filter_map(Instruction::mul)
-        .map(MulData::compute)
-        .sum();
-
-    let (part2, _) = instructions
-        .into_iter()
-        .fold((0, true), |(sum, active), i| match i {
-            Instruction::Mul(m) => (
-                sum + active.then(|| m.compute()).unwrap_or_default(),
-                active,
-            ),
-            Instruction::Do => (sum, true),
-            Instruction::DoNot => (sum, false),
-        });
-
-    (part1, part2)
-}
-
-fn main() -> impl Termination {
-    aoc2024::execute_day(3, solver)
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::{parse, parse_mul, solver, Instruction};
-
+    use crate::{conditional_machine, format_step, mul_machine, parse_mul, part1, part2};
+    use aoc2024::generate::Options;
+    use aoc2024::parsing::combinators::Parser;
+    use aoc2024::random::Rng;
+    use aoc2024::DayTest;
+    use regex::Regex;
+
+    const PART1_EXAMPLE_INPUT: &str =
+        "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
     const EXAMPLE_INPUT: &str =
         "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
 
     #[test]
     fn test_day() {
-        aoc2024::test_day(solver, EXAMPLE_INPUT, (161, Some(48)));
+        DayTest::new(part1, part2)
+            .part1(PART1_EXAMPLE_INPUT, 161)
+            .part2(EXAMPLE_INPUT, 48)
+            .run();
     }
 
     #[test]
-    fn test_parse() {
+    fn test_trace() {
+        let trace = conditional_machine().trace(EXAMPLE_INPUT);
+
         assert_eq!(
-            parse(EXAMPLE_INPUT),
+            trace.iter().map(format_step).collect::<Vec<_>>(),
             [
-                Instruction::new_mul(2, 4),
-                Instruction::DoNot,
-                Instruction::new_mul(5, 5),
-                Instruction::new_mul(11, 8),
-                Instruction::Do,
-                Instruction::new_mul(8, 5)
+                "     1: mul(2,4) enabled=true accumulator=8",
+                "    20: don't() enabled=false accumulator=8",
+                "    28: mul(5,5) enabled=false accumulator=8",
+                "    48: mul(11,8) enabled=false accumulator=8",
+                "    59: do() enabled=true accumulator=8",
+                "    64: mul(8,5) enabled=true accumulator=48",
             ]
         );
     }
 
     #[test]
     fn test_parse_mul() {
-        assert_eq!(parse_mul(""), None);
-        assert_eq!(parse_mul("xab"), None);
-        assert_eq!(parse_mul("mul("), None);
-        assert_eq!(parse_mul("mul(2,5)"), Some((Instruction::new_mul(2, 5), 8)));
+        assert_eq!(parse_mul().parse_at("", 0), None);
+        assert_eq!(parse_mul().parse_at("xab", 0), None);
+        assert_eq!(parse_mul().parse_at("mul(", 0), None);
+        assert_eq!(parse_mul().parse_at("mul(2,5)", 0), Some((vec![2, 5], 8)));
+        assert_eq!(parse_mul().parse_at("mul(20,5)", 0), Some((vec![20, 5], 9)));
         assert_eq!(
-            parse_mul("mul(20,5)"),
-            Some((Instruction::new_mul(20, 5), 9))
+            parse_mul().parse_at("mul(20,50)", 0),
+            Some((vec![20, 50], 10))
         );
         assert_eq!(
-            parse_mul("mul(20,50)"),
-            Some((Instruction::new_mul(20, 50), 10))
+            parse_mul().parse_at("mul(200,500)", 0),
+            Some((vec![200, 500], 12))
         );
+        assert_eq!(parse_mul().parse_at("mul(1234,5)", 0), None);
+        assert_eq!(parse_mul().parse_at("mul(5,1234)", 0), None);
+        assert_eq!(parse_mul().parse_at("mul(+5,3)", 0), None);
+        assert_eq!(parse_mul().parse_at("mul(5,-3)", 0), None);
+        assert_eq!(parse_mul().parse_at("mul( 5,3)", 0), None);
         assert_eq!(
-            parse_mul("mul(200,500)"),
-            Some((Instruction::new_mul(200, 500), 12))
+            parse_mul().parse_at("mul(005,3)", 0),
+            Some((vec![5, 3], 10))
+        );
+    }
+
+    /// Builds memory from `mul` instructions where every part is likely to be corrupted.
+    fn corrupted_memory(rng: &mut Rng) -> String {
+        const NOISE: &[&str] = &["mul", "(", ")", ",", "+", "-", " ", "x", "don't()", "mul["];
+
+        let mut memory = String::new();
+        for _ in 0..rng.range(0..8) {
+            for part in ["mul(", "", ",", "", ")"] {
+                if rng.chance(0.1) {
+                    memory += *rng.choose(NOISE);
+                } else if part.is_empty() {
+                    if rng.chance(0.1) {
+                        memory += *rng.choose(&["+", "-"]);
+                    }
+                    for _ in 0..rng.range(0..=4) {
+                        memory.push(char::from(b'0' + rng.range(0..10u8)));
+                    }
+                } else {
+                    memory += part;
+                }
+            }
+        }
+        memory
+    }
+
+    #[test]
+    fn test_parse_mul_matches_regex() {
+        let reference = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").unwrap();
+        let mut rng = Rng::new(3);
+        let mut found = 0;
+
+        for _ in 0..5000 {
+            let memory = corrupted_memory(&mut rng);
+
+            let expected = reference
+                .captures_iter(&memory)
+                .map(|c| {
+                    let m = c.get(0).unwrap();
+                    (
+                        m.start(),
+                        m.end(),
+                        vec![c[1].parse().unwrap(), c[2].parse().unwrap()],
+                    )
+                })
+                .collect::<Vec<_>>();
+            let actual = mul_machine()
+                .trace(&memory)
+                .into_iter()
+                .map(|s| (s.start, s.end, s.operands))
+                .collect::<Vec<_>>();
+
+            assert_eq!(actual, expected, "instructions differ for {memory:?}");
+            found += expected.len();
+        }
+
+        assert!(found > 100, "only {found} instructions were generated");
+    }
+
+    fn reference(input: &str) -> (i64, i64) {
+        let instruction = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)|do\(\)|don't\(\)").unwrap();
+        let mut enabled = true;
+        let (mut part1, mut part2) = (0, 0);
+
+        for c in instruction.captures_iter(input) {
+            match &c[0] {
+                "do()" => enabled = true,
+                "don't()" => enabled = false,
+                _ => {
+                    let product = c[1].parse::<i64>().unwrap() * c[2].parse::<i64>().unwrap();
+                    part1 += product;
+                    if enabled {
+                        part2 += product;
+                    }
+                }
+            }
+        }
+
+        (part1, part2)
+    }
+
+    #[test]
+    fn test_differential() {
+        let options = Options {
+            size: 400,
+            difficulty: 0.8,
+        };
+        aoc2024::differential_test_day(
+            3,
+            |input| (part1(input), part2(input)),
+            reference,
+            options,
+            20,
         );
     }
 }
@@ -1,10 +1,17 @@
+use aoc2024::cli;
+use aoc2024::render::{self, Cell, Grid, Rgb};
+use std::collections::HashSet;
 use std::ops::{Add, Mul};
 use std::process::Termination;
 
-const XMAS: &[u8; 4] = b"XMAS";
-const MAS: &[u8; 3] = b"MAS";
+const XMAS: &str = "XMAS";
 
-#[derive(Copy, Clone)]
+/// The X-MAS shape of part 2; any rotation of it counts.
+const X_MAS: &str = "M.S
+.A.
+M.S";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 struct Position(usize, usize);
 
 impl Add<Direction> for Position {
@@ -12,15 +19,28 @@ impl Add<Direction> for Position {
 
     fn add(self, rhs: Direction) -> Self::Output {
         Some(Self(
-            (self.0 as i64).checked_add(rhs.0)? as usize,
-            (self.1 as i64).checked_add(rhs.1)? as usize,
+            (self.0 as i64).checked_add(rhs.0)?.try_into().ok()?,
+            (self.1 as i64).checked_add(rhs.1)?.try_into().ok()?,
         ))
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 struct Direction(i64, i64);
 
+impl Direction {
+    const ALL: [Direction; 8] = [
+        Direction(-1, -1),
+        Direction(-1, 0),
+        Direction(-1, 1),
+        Direction(0, -1),
+        Direction(0, 1),
+        Direction(1, -1),
+        Direction(1, 0),
+        Direction(1, 1),
+    ];
+}
+
 impl Mul<usize> for Direction {
     type Output = Direction;
 
@@ -29,74 +49,305 @@ impl Mul<usize> for Direction {
     }
 }
 
-fn check<const N: usize>(
-    input: &[&[u8]],
-    pos: Position,
-    dir: Direction,
-    word: &[u8; N],
-) -> bool {
-    let mut letters: [u8; N] = [0; N];
+/// A word found in a straight line from `start`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Occurrence<'w> {
+    word: &'w str,
+    start: Position,
+    direction: Direction,
+}
+
+impl Occurrence<'_> {
+    fn cells(&self) -> impl Iterator<Item = Position> + '_ {
+        (0..self.word.len()).map(|i| (self.start + self.direction * i).unwrap())
+    }
 
-    if letters
-        .iter_mut()
-        .enumerate()
-        .try_for_each(|(i, l)| {
-            let Position(r, c) = (pos + dir * i)?;
-            *l = *input.get(r)?.get(c)?;
-            Some(())
-        })
-        .is_none()
-    {
-        false
-    } else {
-        &letters == word || letters.iter().rev().eq(word)
+    fn describe(&self) -> String {
+        let Position(r, c) = self.start;
+        let Position(end_r, end_c) = self.cells().last().unwrap_or(self.start);
+        format!(
+            "{} from row {}, column {} to row {}, column {}",
+            self.word,
+            r + 1,
+            c + 1,
+            end_r + 1,
+            end_c + 1
+        )
     }
 }
 
-fn check_x(input: &[&[u8]], pos: Position) -> bool {
-    if let (Some(left_corner), Some(right_corner)) =
-        (pos + Direction(-1, -1), pos + Direction(-1, 1))
-    {
-        check(input, left_corner, Direction(1, 1), MAS)
-            && check(input, right_corner, Direction(1, -1), MAS)
-    } else {
-        false
+/// Which transformations of a pattern also count as matches.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Transforms {
+    rotations: bool,
+    reflections: bool,
+}
+
+/// A rectangular stencil of letters, where wildcard cells match anything.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Pattern {
+    height: usize,
+    width: usize,
+    /// The offsets and letters of all cells that are not wildcards, in sorted order.
+    letters: Vec<(usize, usize, u8)>,
+}
+
+impl Pattern {
+    /// Reads a pattern with one line per row, where `wildcard` matches any letter.
+    fn new(text: &str, wildcard: u8) -> Self {
+        let rows = text.lines().map(str::as_bytes).collect::<Vec<_>>();
+        let letters = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| row.iter().enumerate().map(move |(c, &l)| (r, c, l)))
+            .filter(|&(_, _, l)| l != wildcard)
+            .collect();
+
+        Self {
+            height: rows.len(),
+            width: rows.iter().map(|r| r.len()).max().unwrap_or(0),
+            letters,
+        }
+    }
+
+    fn from_letters(height: usize, width: usize, mut letters: Vec<(usize, usize, u8)>) -> Self {
+        letters.sort_unstable();
+        Self {
+            height,
+            width,
+            letters,
+        }
+    }
+
+    /// Rotates the pattern a quarter turn clockwise.
+    fn rotate(&self) -> Self {
+        let letters = self
+            .letters
+            .iter()
+            .map(|&(r, c, l)| (c, self.height - 1 - r, l))
+            .collect();
+        Self::from_letters(self.width, self.height, letters)
+    }
+
+    /// Mirrors the pattern left to right.
+    fn reflect(&self) -> Self {
+        let letters = self
+            .letters
+            .iter()
+            .map(|&(r, c, l)| (r, self.width - 1 - c, l))
+            .collect();
+        Self::from_letters(self.height, self.width, letters)
+    }
+
+    /// All distinct variants of the pattern under the allowed transformations.
+    fn variants(&self, transforms: Transforms) -> Vec<Pattern> {
+        let mut bases = vec![self.clone()];
+        if transforms.reflections {
+            bases.push(bases[0].reflect());
+        }
+
+        let mut result: Vec<Pattern> = vec![];
+        for base in bases {
+            let mut current = base;
+            for _ in 0..if transforms.rotations { 4 } else { 1 } {
+                let next = current.rotate();
+                if !result.contains(&current) {
+                    result.push(current);
+                }
+                current = next;
+            }
+        }
+        result
     }
 }
 
-fn solver(input: &str) -> (usize, usize) {
-    let data = input.lines().map(|l| l.as_bytes()).collect::<Vec<_>>();
+/// A pattern match, with the grid positions of its letters.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct PatternMatch {
+    origin: Position,
+    cells: Vec<Position>,
+}
 
-    let height = data.len();
-    let width = data.first().expect("at least one row should be present").len();
+struct WordSearch<'a> {
+    rows: Vec<&'a [u8]>,
+}
 
-    const PART1_DIRECTIONS: [Direction; 4] = [
-        Direction(0, 1),
-        Direction(1, 0),
-        Direction(1, 1),
-        Direction(1, -1),
-    ];
+impl<'a> WordSearch<'a> {
+    fn new(input: &'a str) -> Self {
+        let rows = input.lines().map(str::as_bytes).collect::<Vec<_>>();
+        assert!(!rows.is_empty(), "at least one row should be present");
+        Self { rows }
+    }
+
+    fn get(&self, Position(r, c): Position) -> Option<u8> {
+        self.rows.get(r)?.get(c).copied()
+    }
+
+    fn positions(&self) -> impl Iterator<Item = Position> + '_ {
+        self.rows
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| (0..row.len()).map(move |c| Position(r, c)))
+    }
+
+    fn has_word(&self, word: &str, start: Position, direction: Direction) -> bool {
+        word.bytes()
+            .enumerate()
+            .all(|(i, letter)| (start + direction * i).and_then(|p| self.get(p)) == Some(letter))
+    }
 
-    (0..height)
-        .flat_map(|r| (0..width).map(move |c| Position(r, c)))
-        .fold((0, 0), |(p1, p2), pos| {
-            (
-                p1 + PART1_DIRECTIONS
-                    .iter()
-                    .filter(|d| check(&data, pos, **d, XMAS))
-                    .count(),
-                p2 + if check_x(&data, pos) { 1 } else { 0 },
-            )
-        })
+    /// Finds every occurrence of the words in all 8 directions. A palindrome is found twice from
+    /// each start, once in either direction.
+    fn find_words<'w>(&self, words: &[&'w str]) -> Vec<Occurrence<'w>> {
+        self.positions()
+            .flat_map(|start| {
+                words.iter().flat_map(move |&word| {
+                    Direction::ALL
+                        .into_iter()
+                        .filter(move |&direction| self.has_word(word, start, direction))
+                        .map(move |direction| Occurrence {
+                            word,
+                            start,
+                            direction,
+                        })
+                })
+            })
+            .collect()
+    }
+
+    /// Finds every placement of the pattern, or of any of its allowed variants.
+    fn find_pattern(&self, pattern: &Pattern, transforms: Transforms) -> Vec<PatternMatch> {
+        let variants = pattern.variants(transforms);
+
+        self.positions()
+            .flat_map(|origin| {
+                variants.iter().filter_map(move |variant| {
+                    variant
+                        .letters
+                        .iter()
+                        .map(|&(r, c, letter)| {
+                            let position = Position(origin.0 + r, origin.1 + c);
+                            (self.get(position) == Some(letter)).then_some(position)
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .map(|cells| PatternMatch { origin, cells })
+                })
+            })
+            .collect()
+    }
+}
+
+/// The grid with the letters of the found matches highlighted.
+struct Highlight<'s, 'a> {
+    search: &'s WordSearch<'a>,
+    matched: HashSet<Position>,
+    /// Whether to keep unmatched letters, dimmed, instead of replacing them with `.`.
+    show_unmatched: bool,
+}
+
+impl<'s, 'a> Highlight<'s, 'a> {
+    const MATCHED_COLOR: Rgb = [250, 210, 40];
+    const UNMATCHED_COLOR: Rgb = [96, 96, 96];
+
+    fn new(search: &'s WordSearch<'a>, matched: HashSet<Position>, show_unmatched: bool) -> Self {
+        Self {
+            search,
+            matched,
+            show_unmatched,
+        }
+    }
+}
+
+impl Grid for Highlight<'_, '_> {
+    fn width(&self) -> usize {
+        self.search.rows.iter().map(|r| r.len()).max().unwrap_or(0)
+    }
+
+    fn height(&self) -> usize {
+        self.search.rows.len()
+    }
+
+    fn cell(&self, row: usize, column: usize) -> Cell {
+        let position = Position(row, column);
+        match self.search.get(position) {
+            Some(letter) if self.matched.contains(&position) => {
+                Cell::new(letter.into(), Self::MATCHED_COLOR)
+            }
+            Some(letter) if self.show_unmatched => Cell::new(letter.into(), Self::UNMATCHED_COLOR),
+            _ => Cell::new('.', Self::UNMATCHED_COLOR),
+        }
+    }
+}
+
+fn x_mas_transforms() -> Transforms {
+    Transforms {
+        rotations: true,
+        reflections: false,
+    }
+}
+
+/// Draws the letters of the matches of both parts, one grid after the other.
+fn highlight(input: &str, ansi: bool) -> String {
+    let search = WordSearch::new(input);
+    let words = search.find_words(&[XMAS]);
+    let patterns = search.find_pattern(&Pattern::new(X_MAS, b'.'), x_mas_transforms());
+
+    let draw = |matched: HashSet<Position>| {
+        let view = Highlight::new(&search, matched, ansi);
+        if ansi {
+            render::to_ansi(&view)
+        } else {
+            render::to_ascii(&view)
+        }
+    };
+
+    draw(words.iter().flat_map(Occurrence::cells).collect())
+        + "\n"
+        + &draw(patterns.into_iter().flat_map(|m| m.cells).collect())
+}
+
+fn part1(input: &str) -> usize {
+    WordSearch::new(input).find_words(&[XMAS]).len()
+}
+
+fn part2(input: &str) -> usize {
+    let x_mas = Pattern::new(X_MAS, b'.');
+    WordSearch::new(input)
+        .find_pattern(&x_mas, x_mas_transforms())
+        .len()
 }
 
 fn main() -> impl Termination {
-    aoc2024::execute_day(4, solver)
+    let words = cli::option("--words");
+    let render = cli::flag("--render");
+    let ansi = cli::flag("--ansi");
+
+    let result = aoc2024::execute_day(4, part1, part2);
+
+    if words.is_none() && !render && !ansi {
+        return result;
+    }
+
+    let input = aoc2024::day_input(4);
+    if let Some(words) = &words {
+        let words = words.split(',').collect::<Vec<_>>();
+        for occurrence in WordSearch::new(&input).find_words(&words) {
+            println!("{}", occurrence.describe());
+        }
+    }
+    if render || ansi {
+        print!("{}", highlight(&input, ansi));
+    }
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::solver;
+    use crate::{
+        highlight, part1, part2, Direction, Occurrence, Pattern, Position, Transforms, WordSearch,
+    };
+    use aoc2024::generate::Options;
 
     const EXAMPLE_INPUT: &str = "MMMSXXMASM
 MSAMXMSMSA
@@ -111,6 +362,170 @@ MXMXAXMASX";
 
     #[test]
     fn test_day() {
-        aoc2024::test_day(solver, EXAMPLE_INPUT, (18, Some(9)));
+        aoc2024::test_day(part1, part2, EXAMPLE_INPUT, (18, Some(9)));
+    }
+
+    #[test]
+    fn test_highlight() {
+        let expected = "....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+
+.M.S......
+..A..MSMS.
+.M.S.MAA..
+..A.ASMSM.
+.M.S.M....
+..........
+S.S.S.S.S.
+.A.A.A.A..
+M.M.M.M.M.
+..........
+";
+        assert_eq!(highlight(EXAMPLE_INPUT, false), expected);
+
+        let ansi = highlight(EXAMPLE_INPUT, true);
+        assert!(ansi.starts_with("\x1b[38;2;96;96;96mMMMS\x1b[38;2;250;210;40mXXMAS"));
+    }
+
+    #[test]
+    fn test_find_words() {
+        let search = WordSearch::new("TAC\nXAO\nTOC");
+        let found = search.find_words(&["CAT", "TO", "OX"]);
+        let occurrence = |word, start, direction| Occurrence {
+            word,
+            start,
+            direction,
+        };
+
+        assert_eq!(
+            found,
+            [
+                occurrence("CAT", Position(0, 2), Direction(0, -1)),
+                occurrence("CAT", Position(0, 2), Direction(1, -1)),
+                occurrence("TO", Position(2, 0), Direction(0, 1)),
+                occurrence("OX", Position(2, 1), Direction(-1, -1)),
+                occurrence("CAT", Position(2, 2), Direction(-1, -1)),
+            ]
+        );
+        assert_eq!(
+            found[1].cells().collect::<Vec<_>>(),
+            [Position(0, 2), Position(1, 1), Position(2, 0)]
+        );
+        assert_eq!(
+            found[1].describe(),
+            "CAT from row 1, column 3 to row 3, column 1"
+        );
+    }
+
+    #[test]
+    fn test_pattern_variants() {
+        let l_shape = Pattern::new("A.\nBC", b'.');
+        let count = |rotations, reflections| {
+            l_shape
+                .variants(Transforms {
+                    rotations,
+                    reflections,
+                })
+                .len()
+        };
+
+        assert_eq!(count(false, false), 1);
+        assert_eq!(count(true, false), 4);
+        assert_eq!(count(false, true), 2);
+        assert_eq!(count(true, true), 8);
+
+        let bar = Pattern::new("ABA", b'.');
+        assert_eq!(
+            bar.variants(Transforms {
+                rotations: true,
+                reflections: true
+            })
+            .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_find_pattern() {
+        let search = WordSearch::new("ABC\nBAB\nCBA");
+        let pattern = Pattern::new("A?\n?A", b'?');
+        let transforms = Transforms {
+            rotations: false,
+            reflections: false,
+        };
+
+        let found = search.find_pattern(&pattern, transforms);
+        assert_eq!(
+            found.iter().map(|m| m.origin).collect::<Vec<_>>(),
+            [Position(0, 0), Position(1, 1)]
+        );
+        assert_eq!(found[1].cells, [Position(1, 1), Position(2, 2)]);
+
+        let transforms = Transforms {
+            rotations: true,
+            ..transforms
+        };
+        assert_eq!(search.find_pattern(&pattern, transforms).len(), 2);
+        assert_eq!(
+            search
+                .find_pattern(&Pattern::new("B?\n?B", b'?'), transforms)
+                .len(),
+            4
+        );
+    }
+
+    /// Checks every direction from every cell letter by letter.
+    fn reference(input: &str) -> (usize, usize) {
+        let grid = input.lines().map(str::as_bytes).collect::<Vec<_>>();
+        let at = |r: i64, c: i64| {
+            let row = grid.get(usize::try_from(r).ok()?)?;
+            row.get(usize::try_from(c).ok()?).copied()
+        };
+
+        let (mut part1, mut part2) = (0, 0);
+        for r in 0..grid.len() as i64 {
+            for c in 0..grid[r as usize].len() as i64 {
+                for dr in -1..=1 {
+                    for dc in -1..=1 {
+                        part1 += (0..4)
+                            .all(|i| at(r + dr * i, c + dc * i) == Some(b"XMAS"[i as usize]))
+                            as usize;
+                    }
+                }
+
+                let diagonal = |a, b| {
+                    matches!(
+                        (at(r + a, c + b), at(r - a, c - b)),
+                        (Some(b'M'), Some(b'S')) | (Some(b'S'), Some(b'M'))
+                    )
+                };
+                part2 += (at(r, c) == Some(b'A') && diagonal(1, 1) && diagonal(1, -1)) as usize;
+            }
+        }
+
+        (part1, part2)
+    }
+
+    #[test]
+    fn test_differential() {
+        let options = Options {
+            size: 25,
+            difficulty: 0.9,
+        };
+        aoc2024::differential_test_day(
+            4,
+            |input| (part1(input), part2(input)),
+            reference,
+            options,
+            20,
+        );
     }
 }
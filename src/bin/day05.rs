@@ -1,46 +1,296 @@
+use aoc2024::{cli, parsing};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::process::Termination;
 
-fn solver(input: &str) -> (usize, usize) {
-    let (rules, updates) = input.split_once("\n\n").unwrap();
-    let rules = rules
-        .lines()
-        .flat_map(|l| {
-            let (l, r) = l.split_once("|").unwrap();
-            let (l, r) = (l.parse::<usize>().unwrap(), r.parse::<usize>().unwrap());
+/// The ordering rules, each with the line it was defined on.
+struct Rules {
+    lines: HashMap<(usize, usize), usize>,
+}
+
+impl Rules {
+    fn compare(&self, a: usize, b: usize) -> Option<Ordering> {
+        if a == b {
+            Some(Ordering::Equal)
+        } else if self.lines.contains_key(&(a, b)) {
+            Some(Ordering::Less)
+        } else if self.lines.contains_key(&(b, a)) {
+            Some(Ordering::Greater)
+        } else {
+            None
+        }
+    }
+}
+
+struct Update {
+    line: usize,
+    pages: Vec<usize>,
+}
+
+impl Update {
+    fn middle(&self) -> usize {
+        assert_eq!(
+            self.pages.len() % 2,
+            1,
+            "update on line {} should have a middle page",
+            self.line
+        );
+        self.pages[self.pages.len() / 2]
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Problem {
+    /// No rule orders the two pages.
+    MissingRule(usize, usize),
+    /// The rules between the pages form a cycle, so no order satisfies all of them.
+    Cycle(Vec<usize>),
+}
+
+impl Problem {
+    fn describe(&self, rules: &Rules, update: &Update) -> String {
+        match self {
+            Problem::MissingRule(a, b) => format!(
+                "update on line {}: no rule orders pages {a} and {b}",
+                update.line
+            ),
+            Problem::Cycle(pages) => {
+                let steps = pages
+                    .iter()
+                    .zip(pages.iter().cycle().skip(1))
+                    .map(|(a, b)| format!("{a}|{b} (line {})", rules.lines[&(*a, *b)]))
+                    .collect::<Vec<_>>();
+                format!(
+                    "update on line {}: rules form a cycle: {}",
+                    update.line,
+                    steps.join(", ")
+                )
+            }
+        }
+    }
+}
 
-            [((l, r), Ordering::Less), ((r, l), Ordering::Greater)]
+fn parse(input: &str) -> (Rules, Vec<Update>) {
+    let mut sections = parsing::sections(input);
+    let rules = sections.next().expect("rules should be present");
+    let updates = sections.next().expect("updates should be present");
+
+    let rules = Rules {
+        lines: (rules.line..)
+            .zip(rules.text.lines())
+            .map(|(line, l)| (parsing::ints(l).into(), line))
+            .collect(),
+    };
+
+    let updates = (updates.line..)
+        .zip(updates.text.lines())
+        .map(|(line, l)| Update {
+            line,
+            pages: parsing::iter_ints(l).collect(),
         })
-        .collect::<HashMap<_, _>>();
+        .collect();
+
+    (rules, updates)
+}
+
+/// Orders the pages of the update topologically using the rules between them, preferring the
+/// current order where the rules allow either. Fails with a cycle if there is no such order.
+fn topological_order(rules: &Rules, pages: &[usize]) -> Result<Vec<usize>, Problem> {
+    let before = |a: usize, b: usize| a != b && rules.lines.contains_key(&(a, b));
+    let mut in_degree = pages
+        .iter()
+        .map(|&p| pages.iter().filter(|&&q| before(q, p)).count())
+        .collect::<Vec<_>>();
+    let mut placed = vec![false; pages.len()];
+    let mut result = Vec::with_capacity(pages.len());
+
+    while result.len() < pages.len() {
+        let Some(next) = (0..pages.len()).find(|&i| !placed[i] && in_degree[i] == 0) else {
+            let remaining = (0..pages.len())
+                .filter(|&i| !placed[i])
+                .map(|i| pages[i])
+                .collect::<Vec<_>>();
+            return Err(Problem::Cycle(find_cycle(&remaining, before)));
+        };
+
+        placed[next] = true;
+        result.push(pages[next]);
+        for (i, &p) in pages.iter().enumerate() {
+            if before(pages[next], p) {
+                in_degree[i] -= 1;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Finds a cycle among pages that all have a predecessor within `pages`.
+fn find_cycle(pages: &[usize], before: impl Fn(usize, usize) -> bool) -> Vec<usize> {
+    let mut path = vec![pages[0]];
+    let mut seen = HashSet::from([pages[0]]);
+
+    loop {
+        let current = *path.last().unwrap();
+        let previous = *pages
+            .iter()
+            .find(|&&p| before(p, current))
+            .expect("every remaining page should have a predecessor");
+
+        if !seen.insert(previous) {
+            let start = path.iter().position(|&p| p == previous).unwrap();
+            let mut cycle = path.split_off(start);
+            cycle.reverse();
+            return cycle;
+        }
+
+        path.push(previous);
+    }
+}
+
+/// The problems with the rules for an update, and a correct order for it if there is one.
+#[derive(Debug, Eq, PartialEq)]
+struct Validation {
+    problems: Vec<Problem>,
+    order: Option<Vec<usize>>,
+}
+
+/// Checks that the rules order every pair of pages in the update and contain no cycles.
+fn validate(rules: &Rules, pages: &[usize]) -> Validation {
+    let mut problems = (0..pages.len())
+        .flat_map(|i| (i + 1..pages.len()).map(move |j| (pages[i], pages[j])))
+        .filter(|&(a, b)| rules.compare(a, b).is_none())
+        .map(|(a, b)| Problem::MissingRule(a, b))
+        .collect::<Vec<_>>();
+
+    let order = topological_order(rules, pages)
+        .map_err(|cycle| problems.push(cycle))
+        .ok();
+
+    Validation { problems, order }
+}
+
+/// A rule broken by an update: the page at position `later` must come before the page at
+/// position `earlier`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Violation {
+    earlier: usize,
+    later: usize,
+}
+
+/// Finds the violation with the earliest pages, or [None] if the update is ordered.
+fn first_violation(rules: &Rules, pages: &[usize]) -> Option<Violation> {
+    (0..pages.len()).find_map(|earlier| {
+        (earlier + 1..pages.len())
+            .find(|&later| rules.lines.contains_key(&(pages[later], pages[earlier])))
+            .map(|later| Violation { earlier, later })
+    })
+}
+
+fn format_pages(pages: &[usize]) -> String {
+    pages
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Describes why every incorrect update is out of order and how to correct it, followed by the
+/// problems with the rules for every update.
+fn explain(input: &str) -> Vec<String> {
+    let (rules, updates) = parse(input);
+    let mut lines = vec![];
+    let mut warnings = vec![];
+
+    for update in &updates {
+        let pages = &update.pages;
+        let Validation { problems, order } = validate(&rules, pages);
+        warnings.extend(
+            problems
+                .iter()
+                .map(|p| format!("warning: {}", p.describe(&rules, update))),
+        );
+
+        let Some(Violation { earlier, later }) = first_violation(&rules, pages) else {
+            continue;
+        };
+        let (a, b) = (pages[later], pages[earlier]);
+        let correction = match order {
+            Some(order) => format!("corrected order: {}", format_pages(&order)),
+            None => "no correct order exists".to_owned(),
+        };
+
+        lines.push(format!(
+            "update on line {}: {} violates rule {a}|{b} (line {}) with {b} at position {} \
+             and {a} at position {}; {correction}",
+            update.line,
+            format_pages(pages),
+            rules.lines[&(a, b)],
+            earlier + 1,
+            later + 1,
+        ));
+    }
+
+    lines.extend(warnings);
+    lines
+}
+
+/// Sums the middle pages of the ordered updates.
+fn part1(input: &str) -> usize {
+    let (rules, updates) = parse(input);
 
     updates
-        .lines()
-        .map(|l| {
-            l.split(",")
-                .map(|c| c.parse::<usize>().unwrap())
-                .collect::<Vec<_>>()
-        })
-        .fold((0, 0), |(p1, p2), mut update| {
-            assert_eq!(update.len() % 2, 1);
-            if update
-                .is_sorted_by(|&a, &b| matches!(rules[&(a, b)], Ordering::Less | Ordering::Equal))
-            {
-                (p1 + update[update.len() / 2], p2)
-            } else {
-                update.sort_by(|&a, &b| rules[&(a, b)]);
-                (p1, p2 + update[update.len() / 2])
+        .iter()
+        .filter(|update| first_violation(&rules, &update.pages).is_none())
+        .map(Update::middle)
+        .sum()
+}
+
+/// Sums the middle pages of the corrected updates, warning about any problems with the rules for
+/// each update. Updates are corrected with the topological order found while validating them, as
+/// sorting with rules that are missing or cyclic may panic.
+fn part2(input: &str) -> usize {
+    let (rules, updates) = parse(input);
+
+    updates
+        .into_iter()
+        .filter_map(|mut update| {
+            let Validation { problems, order } = validate(&rules, &update.pages);
+            for problem in &problems {
+                eprintln!("warning: {}", problem.describe(&rules, &update));
             }
+
+            first_violation(&rules, &update.pages)?;
+            // Cyclic rules leave no correct order for the update.
+            update.pages = order?;
+            Some(update.middle())
         })
+        .sum()
 }
 
 fn main() -> impl Termination {
-    aoc2024::execute_day(5, solver)
+    let verbose = cli::flag("--verbose");
+
+    let result = aoc2024::execute_day(5, part1, part2);
+
+    if verbose {
+        for line in explain(&aoc2024::day_input(5)) {
+            println!("{line}");
+        }
+    }
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::solver;
+    use crate::{
+        explain, format_pages, parse, part1, part2, topological_order, validate, Problem,
+        Validation,
+    };
+    use aoc2024::generate::Options;
+    use aoc2024::random::Rng;
+    use std::collections::HashSet;
 
     const EXAMPLE_INPUT: &str = "47|53
 97|13
@@ -73,6 +323,188 @@ mod tests {
 
     #[test]
     fn test_day() {
-        aoc2024::test_day(solver, EXAMPLE_INPUT, (143, Some(123)));
+        aoc2024::test_day(part1, part2, EXAMPLE_INPUT, (143, Some(123)));
+    }
+
+    #[test]
+    fn test_explain() {
+        assert_eq!(
+            explain(EXAMPLE_INPUT),
+            [
+                "update on line 26: 75,97,47,61,53 violates rule 97|75 (line 16) with 75 at \
+                 position 1 and 97 at position 2; corrected order: 97,75,47,61,53",
+                "update on line 27: 61,13,29 violates rule 29|13 (line 8) with 13 at position 2 \
+                 and 29 at position 3; corrected order: 61,29,13",
+                "update on line 28: 97,13,75,29,47 violates rule 75|13 (line 20) with 13 at \
+                 position 2 and 75 at position 3; corrected order: 97,75,47,29,13",
+            ]
+        );
+        assert_eq!(
+            explain("1|2\n2|3\n3|1\n\n3,2,1"),
+            [
+                "update on line 5: 3,2,1 violates rule 2|3 (line 2) with 3 at position 1 and 2 at \
+              position 2; no correct order exists",
+                "warning: update on line 5: rules form a cycle: 1|2 (line 1), 2|3 (line 2), 3|1 \
+                 (line 3)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_rules() {
+        let (rules, updates) = parse("1|2\n\n2,1,3");
+
+        assert_eq!(
+            validate(&rules, &updates[0].pages),
+            Validation {
+                problems: vec![Problem::MissingRule(2, 3), Problem::MissingRule(1, 3)],
+                order: Some(vec![1, 2, 3]),
+            }
+        );
+        assert_eq!(
+            explain("1|2\n\n1,2,3"),
+            [
+                "warning: update on line 3: no rule orders pages 1 and 3",
+                "warning: update on line 3: no rule orders pages 2 and 3",
+            ]
+        );
+        assert_eq!(
+            Problem::MissingRule(2, 3).describe(&rules, &updates[0]),
+            "update on line 3: no rule orders pages 2 and 3"
+        );
+        assert_eq!(
+            topological_order(&rules, &updates[0].pages),
+            Ok(vec![1, 2, 3])
+        );
+        let input = "1|2\n\n2,1,3\n1,3,2";
+        assert_eq!((part1(input), part2(input)), (3, 2));
+    }
+
+    #[test]
+    fn test_cycle() {
+        let (rules, updates) = parse("1|2\n2|3\n3|1\n4|1\n\n4,1,2,3,5");
+        let Validation { problems, order } = validate(&rules, &updates[0].pages);
+        assert_eq!(order, None);
+
+        let Some(cycle @ Problem::Cycle(pages)) = problems.last() else {
+            panic!("expected a cycle, got {problems:?}");
+        };
+        assert_eq!(pages.len(), 3);
+        assert!(cycle
+            .describe(&rules, &updates[0])
+            .starts_with("update on line 6: rules form a cycle: "));
+        let input = "1|2\n2|3\n3|1\n\n1,2,3\n3,2,1";
+        assert_eq!((part1(input), part2(input)), (0, 0));
+    }
+
+    /// An update of the pages `1..=n` in a random order, with the rules that `rule` gives for
+    /// every pair of them.
+    fn long_update(
+        rng: &mut Rng,
+        n: usize,
+        rule: impl Fn(&mut Rng, usize, usize) -> Option<String>,
+    ) -> String {
+        let mut rules = vec![];
+        for a in 1..=n {
+            for b in a + 1..=n {
+                rules.extend(rule(rng, a, b));
+            }
+        }
+        let mut pages = (1..=n).collect::<Vec<_>>();
+        rng.shuffle(&mut pages);
+        format!("{}\n\n{}", rules.join("\n"), format_pages(&pages))
+    }
+
+    #[test]
+    fn test_long_updates() {
+        // Sorting more than 20 elements checks that the comparison is a total order, which it is
+        // not for these rules.
+        let mut rng = Rng::new(5);
+
+        for _ in 0..20 {
+            let cyclic = long_update(&mut rng, 31, |rng, a, b| {
+                Some(if rng.chance(0.5) {
+                    format!("{a}|{b}")
+                } else {
+                    format!("{b}|{a}")
+                })
+            });
+            let (rules, updates) = parse(&cyclic);
+            let Validation { problems, order } = validate(&rules, &updates[0].pages);
+            assert!(matches!(problems[..], [Problem::Cycle(_)]));
+            assert_eq!(order, None);
+            assert_eq!(part2(&cyclic), 0);
+
+            // Adjacent pages are always ordered, so the order of the others still follows.
+            let missing = long_update(&mut rng, 33, |rng, a, b| {
+                (b == a + 1 || rng.chance(0.8)).then(|| format!("{a}|{b}"))
+            });
+            let (rules, updates) = parse(&missing);
+            let Validation { problems, order } = validate(&rules, &updates[0].pages);
+            assert!(problems
+                .iter()
+                .all(|p| matches!(p, Problem::MissingRule(..))));
+            assert_eq!(order, Some((1..=33).collect()));
+            assert_eq!(part2(&missing), 17);
+        }
+    }
+
+    /// Swaps adjacent pages until no rule is violated. Rejects updates with pages that no rule
+    /// orders, as the puzzle does not define an order for them.
+    fn reference(input: &str) -> (usize, usize) {
+        let (rules, updates) = input.split_once("\n\n").unwrap();
+        let rules = rules
+            .lines()
+            .map(|l| {
+                let (a, b) = l.split_once('|').unwrap();
+                (a.parse().unwrap(), b.parse().unwrap())
+            })
+            .collect::<HashSet<(usize, usize)>>();
+
+        updates.lines().fold((0, 0), |(p1, p2), line| {
+            let mut pages = line
+                .split(',')
+                .map(|p| p.parse().unwrap())
+                .collect::<Vec<usize>>();
+            for (i, a) in pages.iter().enumerate() {
+                for b in &pages[i + 1..] {
+                    assert!(rules.contains(&(*a, *b)) != rules.contains(&(*b, *a)));
+                }
+            }
+
+            let mut sorted = true;
+            let mut swapped = true;
+            while swapped {
+                swapped = false;
+                for i in 1..pages.len() {
+                    if rules.contains(&(pages[i], pages[i - 1])) {
+                        pages.swap(i, i - 1);
+                        (sorted, swapped) = (false, true);
+                    }
+                }
+            }
+
+            let middle = pages[pages.len() / 2];
+            if sorted {
+                (p1 + middle, p2)
+            } else {
+                (p1, p2 + middle)
+            }
+        })
+    }
+
+    #[test]
+    fn test_differential() {
+        let options = Options {
+            size: 40,
+            difficulty: 0.5,
+        };
+        aoc2024::differential_test_day(
+            5,
+            |input| (part1(input), part2(input)),
+            reference,
+            options,
+            10,
+        );
     }
 }
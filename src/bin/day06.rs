@@ -1,7 +1,18 @@
-use std::collections::HashSet;
+use aoc2024::render::{self, Animation, Cell, Grid, Rgb};
+use aoc2024::{cli, Budgets};
+use std::fs::File;
+use std::io::BufWriter;
 use std::process::Termination;
+use std::thread;
+use std::time::Duration;
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+/// Finding the loops tries every position on the path, which must not get out of hand.
+const BUDGETS: Budgets = Budgets {
+    part1: None,
+    part2: Some(Duration::from_secs(1)),
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 enum Direction {
     North,
     East,
@@ -10,6 +21,13 @@ enum Direction {
 }
 
 impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
     fn rotate_clockwise(self) -> Self {
         match self {
             Direction::North => Direction::East,
@@ -28,72 +46,365 @@ impl Direction {
             _ => None,
         }
     }
+
+    fn arrow(self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::East => '>',
+            Direction::South => 'v',
+            Direction::West => '<',
+        }
+    }
+
+    /// Moves `steps` cells, which the caller guarantees stays inside the lab.
+    fn advance(self, pos: (usize, usize), steps: usize) -> (usize, usize) {
+        match self {
+            Direction::North => (pos.0 - steps, pos.1),
+            Direction::East => (pos.0, pos.1 + steps),
+            Direction::South => (pos.0 + steps, pos.1),
+            Direction::West => (pos.0, pos.1 - steps),
+        }
+    }
+
+    /// The number of steps from `pos` to `target` when `target` lies straight ahead.
+    fn distance(self, pos: (usize, usize), target: (usize, usize)) -> Option<usize> {
+        match self {
+            Direction::North if pos.1 == target.1 && target.0 < pos.0 => Some(pos.0 - target.0),
+            Direction::East if pos.0 == target.0 && target.1 > pos.1 => Some(target.1 - pos.1),
+            Direction::South if pos.1 == target.1 && target.0 > pos.0 => Some(target.0 - pos.0),
+            Direction::West if pos.0 == target.0 && target.1 < pos.1 => Some(pos.1 - target.1),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+struct Jump {
+    /// The number of cells to the next wall, or to just past the edge of the lab.
+    distance: usize,
+    /// Whether the guard walks out of the lab rather than into a wall.
+    exits: bool,
 }
 
-fn walk(
-    position: (usize, usize),
+/// The lab as a dense grid.
+struct Lab {
     width: usize,
     height: usize,
-    walls: &HashSet<(usize, usize)>,
-) -> impl Iterator<Item = ((usize, usize), Direction)> + use<'_> {
-    std::iter::successors(Some((position, Direction::North)), move |&(pos, dir)| {
-        let ahead = dir.apply(width, height, pos)?;
-        if walls.contains(&ahead) {
-            Some((pos, dir.rotate_clockwise()))
-        } else {
-            Some((ahead, dir))
+    walls: Vec<bool>,
+}
+
+impl Lab {
+    fn new(width: usize, height: usize, walls: Vec<bool>) -> Self {
+        Self {
+            width,
+            height,
+            walls,
         }
+    }
+
+    fn index(&self, (r, c): (usize, usize)) -> usize {
+        r * self.width + c
+    }
+
+    fn is_wall(&self, pos: (usize, usize)) -> bool {
+        self.walls[self.index(pos)]
+    }
+
+    /// Walks the guard one cell at a time.
+    fn walk(
+        &self,
+        position: (usize, usize),
+    ) -> impl Iterator<Item = ((usize, usize), Direction)> + use<'_> {
+        std::iter::successors(Some((position, Direction::North)), move |&(pos, dir)| {
+            let ahead = dir.apply(self.width, self.height, pos)?;
+            if self.is_wall(ahead) {
+                Some((pos, dir.rotate_clockwise()))
+            } else {
+                Some((ahead, dir))
+            }
+        })
+    }
+}
+
+/// How far the guard can walk from every cell of a lab in every direction before hitting a wall,
+/// so that the guard can be moved from wall to wall.
+struct Jumps<'a> {
+    lab: &'a Lab,
+    jumps: Vec<[Jump; 4]>,
+}
+
+impl<'a> Jumps<'a> {
+    fn new(lab: &'a Lab) -> Self {
+        let mut jumps = Self {
+            lab,
+            jumps: vec![[Jump::default(); 4]; lab.walls.len()],
+        };
+
+        // Visit cells so that the neighbour in each direction has always been computed already.
+        for r in 0..lab.height {
+            for c in 0..lab.width {
+                jumps.compute((r, c), Direction::North);
+                jumps.compute((r, c), Direction::West);
+            }
+        }
+        for r in (0..lab.height).rev() {
+            for c in (0..lab.width).rev() {
+                jumps.compute((r, c), Direction::East);
+                jumps.compute((r, c), Direction::South);
+            }
+        }
+
+        jumps
+    }
+
+    fn compute(&mut self, pos: (usize, usize), dir: Direction) {
+        let lab = self.lab;
+        let jump = match dir.apply(lab.width, lab.height, pos) {
+            None => Jump {
+                distance: 1,
+                exits: true,
+            },
+            Some(ahead) if lab.is_wall(ahead) => Jump {
+                distance: 1,
+                exits: false,
+            },
+            Some(ahead) => {
+                let next = self.jumps[lab.index(ahead)][dir as usize];
+                Jump {
+                    distance: next.distance + 1,
+                    ..next
+                }
+            }
+        };
+
+        self.jumps[lab.index(pos)][dir as usize] = jump;
+    }
+
+    /// Simulates the guard from wall to wall with an extra `obstacle`, recording the states after
+    /// every turn in `seen` under `generation` to detect a loop.
+    fn loops_with_obstacle(
+        &self,
+        mut pos: (usize, usize),
+        mut dir: Direction,
+        obstacle: (usize, usize),
+        seen: &mut [u32],
+        generation: u32,
+    ) -> bool {
+        loop {
+            let Jump {
+                mut distance,
+                mut exits,
+            } = self.jumps[self.lab.index(pos)][dir as usize];
+
+            if let Some(d) = dir.distance(pos, obstacle).filter(|&d| d < distance) {
+                distance = d;
+                exits = false;
+            }
+
+            if exits {
+                return false;
+            }
+
+            pos = dir.advance(pos, distance - 1);
+            dir = dir.rotate_clockwise();
+
+            let state = self.lab.index(pos) * 4 + dir as usize;
+            if seen[state] == generation {
+                return true;
+            }
+            seen[state] = generation;
+        }
+    }
+}
+
+/// An obstacle placed on the path of the guard, along with the state just before reaching it.
+type Candidate = ((usize, usize), Direction, (usize, usize));
+
+/// Walks the guard, returning the number of visited positions and the obstacle candidates.
+/// Each candidate is the first time the guard is about to enter an empty cell; since the path up
+/// to that point is unaffected by the obstacle, a loop check can start from there. The starting
+/// position is never a candidate, as the guard would notice the obstacle being placed.
+fn explore(lab: &Lab, position: (usize, usize)) -> (usize, Vec<Candidate>) {
+    let mut visited = vec![false; lab.walls.len()];
+    let mut tested = vec![false; lab.walls.len()];
+    tested[lab.index(position)] = true;
+    let mut candidates = vec![];
+
+    for (pos, dir) in lab.walk(position) {
+        visited[lab.index(pos)] = true;
+
+        if let Some(ahead) = dir.apply(lab.width, lab.height, pos) {
+            let index = lab.index(ahead);
+            if !lab.walls[index] && !tested[index] {
+                tested[index] = true;
+                candidates.push((pos, dir, ahead));
+            }
+        }
+    }
+
+    (visited.into_iter().filter(|&v| v).count(), candidates)
+}
+
+/// Finds the candidate obstacles that make the guard walk in a loop.
+fn find_loops(lab: &Lab, candidates: &[Candidate], threads: usize) -> Vec<(usize, usize)> {
+    let jumps = &Jumps::new(lab);
+    let chunk_size = candidates.len().div_ceil(threads).max(1);
+
+    thread::scope(|s| {
+        let handles = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                s.spawn(move || {
+                    let mut seen = vec![0; lab.walls.len() * 4];
+                    (1..)
+                        .zip(chunk)
+                        .filter(|&(generation, &(pos, dir, obstacle))| {
+                            jumps.loops_with_obstacle(pos, dir, obstacle, &mut seen, generation)
+                        })
+                        .map(|(_, &(_, _, obstacle))| obstacle)
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
     })
 }
 
-fn count_positions(
-    position: (usize, usize),
-    width: usize,
-    height: usize,
-    walls: &HashSet<(usize, usize)>,
-) -> usize {
-    let visited = walk(position, width, height, walls)
-        .map(|(pos, _)| pos)
-        .collect::<HashSet<_>>();
+/// The lab with the part of the path walked so far and the obstacles that create loops.
+struct PathView<'a> {
+    lab: &'a Lab,
+    /// Bit `d` is set for every direction `d` the guard has walked through a cell in.
+    directions: Vec<u8>,
+    obstacles: Vec<bool>,
+    guard: Option<((usize, usize), Direction)>,
+}
+
+impl<'a> PathView<'a> {
+    const WALL: Cell = Cell::new('#', [96, 96, 96]);
+    const FLOOR: Cell = Cell::new('.', [16, 16, 16]);
+    const OBSTACLE: Cell = Cell::new('O', [220, 50, 50]);
+    const PATH_COLOR: Rgb = [60, 120, 220];
+    const GUARD_COLOR: Rgb = [250, 210, 40];
 
-    visited.len()
+    fn new(lab: &'a Lab) -> Self {
+        Self {
+            lab,
+            directions: vec![0; lab.walls.len()],
+            obstacles: vec![false; lab.walls.len()],
+            guard: None,
+        }
+    }
+
+    fn visit(&mut self, pos: (usize, usize), dir: Direction) {
+        self.directions[self.lab.index(pos)] |= 1 << dir as u8;
+        self.guard = Some((pos, dir));
+    }
 }
 
-fn count_loops(
-    position: (usize, usize),
-    width: usize,
-    height: usize,
-    walls: &HashSet<(usize, usize)>,
-) -> usize {
-    let mut new_walls = walls.clone();
-    let mut visited = HashSet::new();
-
-    walk(position, width, height, walls)
-        .filter_map(|(p, d)| d.apply(width, height, p))
-        .filter(|&(r, c)| {
-            if !walls.contains(&(r, c)) && visited.insert((r, c)) {
-                new_walls.insert((r, c));
-                let loops = loops(position, width, height, &new_walls);
-                new_walls.remove(&(r, c));
-                loops
-            } else {
-                false
+impl Grid for PathView<'_> {
+    fn width(&self) -> usize {
+        self.lab.width
+    }
+
+    fn height(&self) -> usize {
+        self.lab.height
+    }
+
+    fn cell(&self, row: usize, column: usize) -> Cell {
+        let index = self.lab.index((row, column));
+
+        match self.guard {
+            Some((pos, dir)) if pos == (row, column) => Cell::new(dir.arrow(), Self::GUARD_COLOR),
+            _ if self.lab.walls[index] => Self::WALL,
+            _ if self.obstacles[index] => Self::OBSTACLE,
+            _ => {
+                let directions = self.directions[index];
+                let vertical = 1 << Direction::North as u8 | 1 << Direction::South as u8;
+                let symbol = match directions {
+                    0 => return Self::FLOOR,
+                    d if d.count_ones() == 1 => Direction::ALL[d.trailing_zeros() as usize].arrow(),
+                    d if d & vertical == d => '|',
+                    d if d & vertical == 0 => '-',
+                    _ => '+',
+                };
+
+                Cell::new(symbol, Self::PATH_COLOR)
             }
-        })
-        .count()
+        }
+    }
 }
 
-fn loops(
-    position: (usize, usize),
-    width: usize,
-    height: usize,
-    walls: &HashSet<(usize, usize)>,
-) -> bool {
-    let mut visited_states = HashSet::new();
-    walk(position, width, height, walls).any(|s| !visited_states.insert(s))
+/// Which drawings of the guard path were requested on the command line.
+struct RenderOptions {
+    ascii: bool,
+    png: Option<String>,
+    gif: Option<String>,
+}
+
+impl RenderOptions {
+    const SCALE: usize = 4;
+    const FRAMES: usize = 100;
+
+    fn from_args() -> Self {
+        Self {
+            ascii: cli::flag("--render"),
+            png: cli::option("--png"),
+            gif: cli::option("--gif"),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.ascii || self.png.is_some() || self.gif.is_some()
+    }
+
+    fn draw(&self, input: &str, threads: usize) {
+        let (lab, position) = parse(input);
+        let (_, candidates) = explore(&lab, position);
+        let mut view = PathView::new(&lab);
+
+        let mut animation = self.gif.as_ref().map(|path| {
+            let file = File::create(path).expect("GIF file should be writable");
+            Animation::new(BufWriter::new(file), &view, Self::SCALE, 5)
+                .expect("animation should be created")
+        });
+
+        let states = lab.walk(position).collect::<Vec<_>>();
+        let step = states.len().div_ceil(Self::FRAMES);
+        for (i, &(pos, dir)) in states.iter().enumerate() {
+            view.visit(pos, dir);
+            if let Some(animation) = animation.as_mut().filter(|_| i % step == 0) {
+                animation.add_frame(&view).expect("frame should be written");
+            }
+        }
+
+        view.guard = None;
+        for obstacle in find_loops(&lab, &candidates, threads) {
+            view.obstacles[lab.index(obstacle)] = true;
+        }
+
+        if let (Some(animation), Some(path)) = (&mut animation, &self.gif) {
+            animation.add_frame(&view).expect("frame should be written");
+            println!("Wrote animation to {path}");
+        }
+
+        if self.ascii {
+            print!("{}", render::to_ascii(&view));
+        }
+
+        if let Some(path) = &self.png {
+            let file = File::create(path).expect("PNG file should be writable");
+            render::write_png(&view, Self::SCALE, BufWriter::new(file))
+                .expect("image should be written");
+            println!("Wrote image to {path}");
+        }
+    }
 }
 
-fn solver(input: &str) -> (usize, usize) {
+fn parse(input: &str) -> (Lab, (usize, usize)) {
     let position = input
         .lines()
         .enumerate()
@@ -104,28 +415,52 @@ fn solver(input: &str) -> (usize, usize) {
 
     let walls = input
         .lines()
-        .enumerate()
-        .flat_map(|(r, row)| {
-            row.chars()
-                .enumerate()
-                .filter(|&(_, t)| t == '#')
-                .map(move |(c, _)| (r, c))
-        })
-        .collect::<HashSet<_>>();
+        .flat_map(|row| row.bytes().map(|t| t == b'#'))
+        .collect::<Vec<_>>();
+    assert_eq!(walls.len(), width * height, "lab should be rectangular");
+
+    (Lab::new(width, height, walls), position)
+}
+
+fn part1(input: &str) -> usize {
+    let (lab, position) = parse(input);
+    explore(&lab, position).0
+}
+
+fn part2(input: &str, threads: usize) -> usize {
+    let (lab, position) = parse(input);
+    let (_, candidates) = explore(&lab, position);
 
-    (
-        count_positions(position, width, height, &walls),
-        count_loops(position, width, height, &walls),
-    )
+    find_loops(&lab, &candidates, threads).len()
 }
 
 fn main() -> impl Termination {
-    aoc2024::execute_day(6, solver)
+    let threads = if cli::flag("--parallel") {
+        thread::available_parallelism().map_or(1, |n| n.get())
+    } else {
+        1
+    };
+
+    let render = RenderOptions::from_args();
+
+    let result = aoc2024::execute_day_within(6, BUDGETS, part1, move |input| part2(input, threads));
+
+    // Rendering finds the loops all over again, so it must not count towards the budget.
+    if render.is_enabled() {
+        render.draw(&aoc2024::day_input(6), threads);
+    }
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::solver;
+    use crate::{explore, find_loops, parse, part1, part2, Lab, PathView, BUDGETS};
+    use aoc2024::generate::Options;
+    use aoc2024::random::Rng;
+    use aoc2024::render;
+    use aoc2024::DayTest;
+    use std::collections::HashSet;
 
     const EXAMPLE_INPUT: &str = "....#.....
 .........#
@@ -140,6 +475,164 @@ mod tests {
 
     #[test]
     fn test_day() {
-        aoc2024::test_day(solver, EXAMPLE_INPUT, (41, Some(6)));
+        for threads in [1, 4] {
+            DayTest::new(part1, move |input: &str| part2(input, threads))
+                .part1(EXAMPLE_INPUT, 41)
+                .part2(EXAMPLE_INPUT, 6)
+                .run();
+        }
+    }
+
+    #[test]
+    fn test_real_input_within_budget() {
+        aoc2024::test_real_input_within(6, BUDGETS, part1, |input| part2(input, 4));
+    }
+
+    #[test]
+    fn test_start_is_not_a_candidate() {
+        // The guard returns to its starting position, which would otherwise close a loop.
+        let input = "..#.\n...#\n.#..\n.#^.";
+        assert_eq!((part1(input), part2(input, 1)), (3, 0));
+    }
+
+    #[test]
+    fn test_render() {
+        let (lab, position) = parse(EXAMPLE_INPUT);
+        let (_, candidates) = explore(&lab, position);
+        let mut view = PathView::new(&lab);
+        lab.walk(position)
+            .for_each(|(pos, dir)| view.visit(pos, dir));
+        view.guard = None;
+        for obstacle in find_loops(&lab, &candidates, 1) {
+            view.obstacles[lab.index(obstacle)] = true;
+        }
+
+        assert_eq!(
+            render::to_ascii(&view),
+            "....#.....
+....+>>>+#
+....^...v.
+..#.^...v.
+..+>+>+#v.
+..^.^.v.v.
+.#+O+<+<+.
+.+>>>>OO#.
+#O<O<<+v..
+......#O..
+"
+        );
+    }
+
+    /// Re-walks the whole path one cell at a time for every candidate.
+    fn count_loops_naive(lab: &mut Lab, position: (usize, usize)) -> usize {
+        let (_, candidates) = explore(lab, position);
+
+        candidates
+            .into_iter()
+            .filter(|&(_, _, obstacle)| {
+                let index = lab.index(obstacle);
+                lab.walls[index] = true;
+                let mut states = HashSet::new();
+                let loops = lab.walk(position).any(|s| !states.insert(s));
+                lab.walls[index] = false;
+                loops
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let mut rng = Rng::new(6);
+
+        for _ in 0..200 {
+            let (width, height) = (rng.range(1..16), rng.range(1..16));
+            let mut input = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| if rng.chance(0.15) { '#' } else { '.' })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>();
+            let start = (rng.range(0..height), rng.range(0..width));
+            input[start.0].replace_range(start.1..start.1 + 1, "^");
+            let input = input.join("\n");
+
+            let (mut lab, position) = parse(&input);
+            let mut states = HashSet::new();
+            if lab.walk(position).any(|s| !states.insert(s)) {
+                // The guard has to leave the lab for the puzzle to make sense.
+                continue;
+            }
+
+            assert_eq!(
+                part2(&input, 3),
+                count_loops_naive(&mut lab, position),
+                "mismatch for\n{input}"
+            );
+        }
+    }
+
+    /// Walks the guard around the map cell by cell, rejecting maps where it never leaves.
+    /// Returns the visited cells, or [None] if the guard loops.
+    fn walk_naive(map: &[Vec<u8>], start: (i64, i64)) -> Option<HashSet<(i64, i64)>> {
+        let ((mut r, mut c), (mut dr, mut dc)) = (start, (-1, 0));
+        let mut states = HashSet::new();
+        let cell = |r: i64, c: i64| {
+            let row = map.get(usize::try_from(r).ok()?)?;
+            row.get(usize::try_from(c).ok()?).copied()
+        };
+
+        loop {
+            if !states.insert((r, c, dr, dc)) {
+                return None;
+            }
+            match cell(r + dr, c + dc) {
+                None => return Some(states.into_iter().map(|(r, c, _, _)| (r, c)).collect()),
+                Some(b'#') => (dr, dc) = (dc, -dr),
+                Some(_) => (r, c) = (r + dr, c + dc),
+            }
+        }
+    }
+
+    /// Tries an obstruction on every visited cell and walks the whole path again.
+    fn reference(input: &str) -> (usize, usize) {
+        let mut map = input
+            .lines()
+            .map(|l| l.as_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let start = map
+            .iter()
+            .enumerate()
+            .find_map(|(r, row)| Some((r as i64, row.iter().position(|&c| c == b'^')? as i64)))
+            .expect("the map should contain a guard");
+        let visited = walk_naive(&map, start).expect("the guard should leave the lab");
+
+        let loops = visited
+            .iter()
+            .filter(|&&(r, c)| (r, c) != start)
+            .filter(|&&(r, c)| {
+                map[r as usize][c as usize] = b'#';
+                let loops = walk_naive(&map, start).is_none();
+                map[r as usize][c as usize] = b'.';
+                loops
+            })
+            .count();
+
+        (visited.len(), loops)
+    }
+
+    #[test]
+    fn test_differential() {
+        let options = Options {
+            size: 12,
+            difficulty: 0.6,
+        };
+        aoc2024::differential_test_day(
+            6,
+            |input| (part1(input), part2(input, 2)),
+            reference,
+            options,
+            20,
+        );
     }
 }
@@ -1,3 +1,6 @@
+use aoc2024::algorithms::memo;
+use aoc2024::parsing::{Fields, ParseError};
+use aoc2024::{cli, numeric};
 use std::process::Termination;
 use std::str::FromStr;
 
@@ -7,63 +10,336 @@ struct Equation {
 }
 
 impl FromStr for Equation {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (expected, numbers) = s.split_once(": ").unwrap();
+        let mut fields = Fields::delimited(s, ": ");
+        let expected = fields.parse("test value")?;
+        let numbers = fields.then_whitespace().parse_rest("number")?;
 
-        Ok(Self {
-            expected: expected.parse().unwrap(),
-            numbers: numbers.split(' ').map(|n| n.parse().unwrap()).collect(),
-        })
+        if numbers.is_empty() {
+            return Err(ParseError::new(s, &s[s.len()..], "at least one number"));
+        }
+
+        Ok(Self { expected, numbers })
     }
 }
 
-fn solver(input: &str) -> (i64, i64) {
-    let equations = aoc2024::parsing::parse_list::<Equation>(input).collect::<Vec<_>>();
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+enum Strategy {
+    /// Tries every operator from the first number onwards.
+    Forward,
+    /// Undoes the operators from the expected value backwards, pruning most branches at once.
+    #[default]
+    Reverse,
+}
 
-    fn compute_part<const PART2: bool>(equations: &[Equation]) -> i64 {
-        equations
-            .iter()
-            .filter(|e| search_equation::<PART2>(e.expected, e.numbers[0], &e.numbers[1..]))
-            .map(|e| e.expected)
-            .sum()
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(Strategy::Forward),
+            "reverse" => Ok(Strategy::Reverse),
+            _ => Err("expected \"forward\" or \"reverse\"".to_owned()),
+        }
+    }
+}
+
+impl Strategy {
+    fn search<const PART2: bool>(self, equation: &Equation) -> bool {
+        match self {
+            Strategy::Forward => search_equation::<PART2>(
+                equation.expected,
+                equation.numbers[0],
+                &equation.numbers[1..],
+            ),
+            Strategy::Reverse => {
+                search_equation_reverse::<PART2>(equation.expected, &equation.numbers)
+            }
+        }
     }
+}
 
-    (
-        compute_part::<false>(&equations),
-        compute_part::<true>(&equations),
-    )
+/// Sums the test values of the equations that can be made true, with concatenation in part 2.
+fn solve<const PART2: bool>(input: &str, strategy: Strategy) -> i64 {
+    aoc2024::parsing::parse_list::<Equation>(input)
+        .filter(|e| strategy.search::<PART2>(e))
+        .try_fold(0i64, |sum, e| sum.checked_add(e.expected))
+        .expect("sum of test values should fit in an i64")
 }
 
+/// All operators are non-decreasing for the non-negative numbers of the puzzle as long as the
+/// operand is positive, since multiplying by zero resets the value to zero. Once only positive
+/// numbers remain, the search can give up on a branch as soon as it overshoots `expected`.
+/// Branches that overflow are always given up.
 fn search_equation<const PART2: bool>(expected: i64, value: i64, numbers: &[i64]) -> bool {
-    if let Some(next) = numbers.first().copied() {
-        search_equation::<PART2>(expected, value + next, &numbers[1..])
-            || search_equation::<PART2>(expected, value * next, &numbers[1..])
-            || if PART2 {
-                search_equation::<PART2>(expected, concatenate_numbers(value, next), &numbers[1..])
+    let operators: &[fn(i64, i64) -> Option<i64>] = if PART2 {
+        &[i64::checked_add, i64::checked_mul, concatenate_numbers]
+    } else {
+        &[i64::checked_add, i64::checked_mul]
+    };
+
+    memo::monotone_search(value, numbers, expected, operators, |n| n > 0)
+}
+
+/// Works backwards from `expected`: the last number must have been added, multiplied or
+/// concatenated, so each operator can only be undone if the result is non-negative, divisible or
+/// ends in the digits of that number respectively. Multiplying by zero cannot be undone, but makes
+/// an expected zero reachable from any prefix that evaluates at all.
+fn search_equation_reverse<const PART2: bool>(expected: i64, numbers: &[i64]) -> bool {
+    let Some((&last, rest)) = numbers.split_last() else {
+        return false;
+    };
+
+    if rest.is_empty() {
+        return expected == last;
+    }
+
+    (expected >= last && search_equation_reverse::<PART2>(expected - last, rest))
+        || (last != 0
+            && expected % last == 0
+            && search_equation_reverse::<PART2>(expected / last, rest))
+        || (last == 0 && expected == 0 && evaluates::<PART2>(rest))
+        || (PART2
+            && split_concatenation(expected, last)
+                .is_some_and(|prefix| search_equation_reverse::<PART2>(prefix, rest)))
+}
+
+/// Whether some assignment of operators evaluates `numbers` without overflowing. Every operator is
+/// non-decreasing in its left operand, so it suffices to follow the smallest reachable value.
+fn evaluates<const PART2: bool>(numbers: &[i64]) -> bool {
+    numbers[1..]
+        .iter()
+        .try_fold(numbers[0], |value, &n| {
+            let concatenated = if PART2 {
+                concatenate_numbers(value, n)
             } else {
-                false
-            }
+                None
+            };
+            [value.checked_add(n), value.checked_mul(n), concatenated]
+                .into_iter()
+                .flatten()
+                .min()
+        })
+        .is_some()
+}
+
+/// The inverse of [concatenate_numbers]: finds `a` such that `concatenate_numbers(a, b) == value`.
+fn split_concatenation(value: i64, b: i64) -> Option<i64> {
+    if b == 0 {
+        return Some(value);
+    }
+
+    match numeric::checked_pow10(numeric::digits(b as u64)) {
+        Some(shift) => (value % shift == b).then_some(value / shift),
+        None => (value == b).then_some(0),
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Operator {
+    Add,
+    Multiply,
+    Concatenate,
+}
+
+impl Operator {
+    fn apply(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Multiply => a.checked_mul(b),
+            Operator::Concatenate => concatenate_numbers(a, b),
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Add => "+",
+            Operator::Multiply => "*",
+            Operator::Concatenate => "||",
+        }
+    }
+}
+
+/// The operators satisfying an equation, along with how many assignments satisfy it in total.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Assignment {
+    operators: Vec<Operator>,
+    count: usize,
+}
+
+impl Assignment {
+    /// Evaluates the operators left to right, or returns [None] on overflow.
+    fn evaluate(&self, numbers: &[i64]) -> Option<i64> {
+        self.operators
+            .iter()
+            .zip(&numbers[1..])
+            .try_fold(numbers[0], |value, (operator, &number)| {
+                operator.apply(value, number)
+            })
+    }
+
+    fn format(&self, numbers: &[i64]) -> String {
+        let mut result = numbers[0].to_string();
+        for (operator, number) in self.operators.iter().zip(&numbers[1..]) {
+            result += &format!(" {} {number}", operator.symbol());
+        }
+        result
+    }
+}
+
+/// Finds an operator assignment satisfying the equation and counts all satisfying assignments.
+fn find_assignment<const PART2: bool>(equation: &Equation) -> Option<Assignment> {
+    let mut first = None;
+    let mut count = 0;
+
+    visit_assignments::<PART2>(
+        equation.expected,
+        &equation.numbers,
+        &mut vec![],
+        &mut |reversed| {
+            first.get_or_insert_with(|| reversed.iter().rev().copied().collect());
+            count += 1;
+        },
+    );
+
+    let assignment = first.map(|operators| Assignment { operators, count });
+    debug_assert!(assignment
+        .as_ref()
+        .is_none_or(|a| a.evaluate(&equation.numbers) == Some(equation.expected)));
+    assignment
+}
+
+/// Like [search_equation_reverse], but visits every satisfying assignment.
+/// The operators are passed to `visit` in reverse order.
+fn visit_assignments<const PART2: bool>(
+    expected: i64,
+    numbers: &[i64],
+    operators: &mut Vec<Operator>,
+    visit: &mut impl FnMut(&[Operator]),
+) {
+    let Some((&last, rest)) = numbers.split_last() else {
+        return;
+    };
+
+    if rest.is_empty() {
+        if expected == last {
+            visit(operators);
+        }
+        return;
+    }
+
+    let candidates = [
+        (Operator::Add, (expected >= last).then(|| expected - last)),
+        (
+            Operator::Multiply,
+            (last != 0 && expected % last == 0).then(|| expected / last),
+        ),
+        (
+            Operator::Concatenate,
+            split_concatenation(expected, last).filter(|_| PART2),
+        ),
+    ];
+
+    for (operator, prefix) in candidates {
+        if let Some(prefix) = prefix {
+            operators.push(operator);
+            visit_assignments::<PART2>(prefix, rest, operators, visit);
+            operators.pop();
+        }
+    }
+
+    // Multiplying by zero reaches zero from every prefix that evaluates at all.
+    if last == 0 && expected == 0 {
+        operators.push(Operator::Multiply);
+        let depth = operators.len();
+        visit_evaluations::<PART2>(rest[0], &rest[1..], &mut vec![], &mut |forward| {
+            operators.extend(forward.iter().rev());
+            visit(operators);
+            operators.truncate(depth);
+        });
+        operators.pop();
+    }
+}
+
+/// Visits every assignment of operators that evaluates `numbers` from `value` without overflowing.
+/// Unlike [visit_assignments], the operators are passed to `visit` in order.
+fn visit_evaluations<const PART2: bool>(
+    value: i64,
+    numbers: &[i64],
+    operators: &mut Vec<Operator>,
+    visit: &mut impl FnMut(&[Operator]),
+) {
+    let Some((&next, rest)) = numbers.split_first() else {
+        visit(operators);
+        return;
+    };
+
+    let available: &[Operator] = if PART2 {
+        &[Operator::Add, Operator::Multiply, Operator::Concatenate]
     } else {
-        expected == value
+        &[Operator::Add, Operator::Multiply]
+    };
+
+    for &operator in available {
+        if let Some(value) = operator.apply(value, next) {
+            operators.push(operator);
+            visit_evaluations::<PART2>(value, rest, operators, visit);
+            operators.pop();
+        }
+    }
+}
+
+/// Prints how each equation can be satisfied for both parts.
+fn explain(input: &str) {
+    for equation in aoc2024::parsing::parse_list::<Equation>(input) {
+        let describe = |assignment: Option<Assignment>| match assignment {
+            Some(a) => format!("{} ({} valid)", a.format(&equation.numbers), a.count),
+            None => "unsatisfiable".to_owned(),
+        };
+
+        println!(
+            "{}: part 1: {}; part 2: {}",
+            equation.expected,
+            describe(find_assignment::<false>(&equation)),
+            describe(find_assignment::<true>(&equation)),
+        );
     }
 }
 
-fn concatenate_numbers(a: i64, b: i64) -> i64 {
+fn concatenate_numbers(a: i64, b: i64) -> Option<i64> {
     match (a, b) {
-        (x, 0) | (0, x) => x,
-        (a, b) => a * 10i64.pow(b.ilog10() + 1) + b,
+        (x, 0) | (0, x) => Some(x),
+        (a, b) => numeric::checked_concat(a, b),
     }
 }
 
 fn main() -> impl Termination {
-    aoc2024::execute_day(7, solver)
+    let strategy = cli::parsed_option("--strategy").unwrap_or_default();
+    let verbose = cli::flag("--verbose");
+
+    let result = aoc2024::execute_day(
+        7,
+        move |input| solve::<false>(input, strategy),
+        move |input| solve::<true>(input, strategy),
+    );
+
+    if verbose {
+        explain(&aoc2024::day_input(7));
+    }
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{concatenate_numbers, solver};
+    use crate::{
+        concatenate_numbers, find_assignment, split_concatenation, Equation, Operator, Strategy,
+    };
+    use aoc2024::generate::Options;
+    use aoc2024::random::Rng;
+    use aoc2024::testing;
 
     const EXAMPLE_INPUT: &str = "190: 10 19
 3267: 81 40 27
@@ -75,17 +351,240 @@ mod tests {
 21037: 9 7 18 13
 292: 11 6 16 20";
 
+    fn solve(input: &str, strategy: Strategy) -> (i64, i64) {
+        (
+            crate::solve::<false>(input, strategy),
+            crate::solve::<true>(input, strategy),
+        )
+    }
+
     #[test]
     fn test_day() {
-        aoc2024::test_day(solver, EXAMPLE_INPUT, (3749, Some(11387)));
+        for strategy in [Strategy::Forward, Strategy::Reverse] {
+            aoc2024::test_day(
+                |input| crate::solve::<false>(input, strategy),
+                |input| crate::solve::<true>(input, strategy),
+                EXAMPLE_INPUT,
+                (3749, Some(11387)),
+            );
+        }
+    }
+
+    /// The original search, trying every operator on every number without any pruning.
+    fn brute_force<const PART2: bool>(expected: i64, value: i64, numbers: &[i64]) -> bool {
+        let Some((&next, rest)) = numbers.split_first() else {
+            return expected == value;
+        };
+
+        let mut values = vec![value.checked_add(next), value.checked_mul(next)];
+        if PART2 {
+            values.push(concatenate_numbers(value, next));
+        }
+        values
+            .into_iter()
+            .flatten()
+            .any(|v| brute_force::<PART2>(expected, v, rest))
+    }
+
+    #[test]
+    fn test_strategies_agree() {
+        let mut rng = Rng::new(7);
+
+        for _ in 0..2000 {
+            let numbers = (0..rng.range(1..=6))
+                .map(|_| {
+                    if rng.chance(0.1) {
+                        0
+                    } else {
+                        rng.range(1..100)
+                    }
+                })
+                .collect::<Vec<i64>>();
+            let expected = if rng.chance(0.5) {
+                numbers[1..]
+                    .iter()
+                    .fold(numbers[0], |v, &n| match rng.range(0..3) {
+                        0 => v + n,
+                        1 => v * n,
+                        _ => concatenate_numbers(v, n).unwrap(),
+                    })
+            } else {
+                rng.range(0..100_000)
+            };
+            let equation = Equation { expected, numbers };
+            let (first, rest) = (equation.numbers[0], &equation.numbers[1..]);
+
+            for strategy in [Strategy::Forward, Strategy::Reverse] {
+                assert_eq!(
+                    strategy.search::<false>(&equation),
+                    brute_force::<false>(expected, first, rest),
+                    "part 1 differs for {strategy:?} on {} {:?}",
+                    equation.expected,
+                    equation.numbers
+                );
+                assert_eq!(
+                    strategy.search::<true>(&equation),
+                    brute_force::<true>(expected, first, rest),
+                    "part 2 differs for {strategy:?} on {} {:?}",
+                    equation.expected,
+                    equation.numbers
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_assignment() {
+        let equation = Equation {
+            expected: 3267,
+            numbers: vec![81, 40, 27],
+        };
+        let assignment = find_assignment::<false>(&equation).unwrap();
+        assert_eq!(assignment.count, 2);
+        assert_eq!(assignment.format(&equation.numbers), "81 * 40 + 27");
+
+        let equation = Equation {
+            expected: 7290,
+            numbers: vec![6, 8, 6, 15],
+        };
+        assert_eq!(find_assignment::<false>(&equation), None);
+        let assignment = find_assignment::<true>(&equation).unwrap();
+        assert_eq!(assignment.format(&equation.numbers), "6 * 8 || 6 * 15");
+        assert_eq!(assignment.evaluate(&equation.numbers), Some(7290));
+        assert!(assignment.operators.contains(&Operator::Concatenate));
+
+        let equation = Equation {
+            expected: 0,
+            numbers: vec![7, 3, 0],
+        };
+        let assignment = find_assignment::<true>(&equation).unwrap();
+        assert_eq!(assignment.count, 3);
+        assert_eq!(assignment.format(&equation.numbers), "7 + 3 * 0");
     }
 
     #[test]
     fn test_concatenate() {
-        assert_eq!(concatenate_numbers(12, 345), 12345);
-        assert_eq!(concatenate_numbers(1, 56), 156);
-        assert_eq!(concatenate_numbers(1, 2), 12);
-        assert_eq!(concatenate_numbers(0, 145), 145);
-        assert_eq!(concatenate_numbers(168, 0), 168);
+        assert_eq!(concatenate_numbers(12, 345), Some(12345));
+        assert_eq!(concatenate_numbers(1, 56), Some(156));
+        assert_eq!(concatenate_numbers(1, 2), Some(12));
+        assert_eq!(concatenate_numbers(0, 145), Some(145));
+        assert_eq!(concatenate_numbers(168, 0), Some(168));
+        assert_eq!(concatenate_numbers(i64::MAX / 10, 12), None);
+    }
+
+    #[test]
+    fn test_concatenate_matches_naive() {
+        let number = || testing::ints(0..=i64::MAX);
+
+        testing::check_against(
+            2000,
+            (number(), number()),
+            |&(a, b)| concatenate_numbers(a, b),
+            |&(a, b)| match b {
+                0 => Some(a),
+                _ => format!("{a}{b}").parse().ok(),
+            },
+        );
+    }
+
+    #[test]
+    fn test_zero_operands() {
+        // 7 * 0 + 6 overshoots before the multiplication brings it back down.
+        for strategy in [Strategy::Forward, Strategy::Reverse] {
+            assert_eq!(solve("6: 7 0 6", strategy), (6, 6));
+            assert_eq!(solve("5: 7 0 6", strategy), (0, 0));
+
+            // A final zero reaches zero from any prefix, unless every prefix overflows.
+            let reaches_zero = |numbers: Vec<i64>| {
+                strategy.search::<false>(&Equation {
+                    expected: 0,
+                    numbers,
+                })
+            };
+            assert!(reaches_zero(vec![7, 3, 0]));
+            assert!(!reaches_zero(vec![i64::MAX, 2, 0]));
+        }
+    }
+
+    #[test]
+    fn test_overflow_is_pruned() {
+        let input = "9223372036854775807: 922337203685477580 7\n\
+                     9223372036854775806: 4611686018427387903 2 3";
+
+        for strategy in [Strategy::Forward, Strategy::Reverse] {
+            assert_eq!(solve(input, strategy), (0, 9223372036854775807));
+        }
+    }
+
+    #[test]
+    fn test_split_concatenation() {
+        assert_eq!(split_concatenation(12345, 345), Some(12));
+        assert_eq!(split_concatenation(12345, 45), Some(123));
+        assert_eq!(split_concatenation(12345, 44), None);
+        assert_eq!(split_concatenation(145, 145), Some(0));
+        assert_eq!(split_concatenation(168, 0), Some(168));
+        assert_eq!(
+            split_concatenation(1_000_000_000_000_000_000, 1_000_000_000_000_000_000),
+            Some(0)
+        );
+    }
+
+    /// Evaluates every combination of operators.
+    fn reference(input: &str) -> (i64, i64) {
+        let mut sums = (0, 0);
+
+        for line in input.lines() {
+            let (expected, numbers) = line.split_once(": ").unwrap();
+            let expected = expected.parse::<i64>().unwrap();
+            let numbers = numbers
+                .split(' ')
+                .map(|n| n.parse().unwrap())
+                .collect::<Vec<i64>>();
+
+            let satisfiable = |operators: u32| {
+                (0..3u32.pow(numbers.len() as u32 - 1)).any(|mut combination| {
+                    let value = numbers[1..].iter().try_fold(numbers[0], |value, &n| {
+                        let operator = combination % 3;
+                        combination /= 3;
+                        match operator {
+                            _ if operator >= operators => None,
+                            0 => value.checked_add(n),
+                            1 => value.checked_mul(n),
+                            // Concatenating zero leaves the value as it is, like the puzzle's
+                            // original solution did.
+                            _ if n == 0 => Some(value),
+                            _ => format!("{value}{n}").parse().ok(),
+                        }
+                    });
+                    value == Some(expected)
+                })
+            };
+
+            if satisfiable(2) {
+                sums.0 += expected;
+            }
+            if satisfiable(3) {
+                sums.1 += expected;
+            }
+        }
+
+        sums
+    }
+
+    #[test]
+    fn test_differential() {
+        let options = Options {
+            size: 40,
+            difficulty: 0.4,
+        };
+        for strategy in [Strategy::Forward, Strategy::Reverse] {
+            aoc2024::differential_test_day(
+                7,
+                |input| solve(input, strategy),
+                reference,
+                options,
+                10,
+            );
+        }
     }
 }
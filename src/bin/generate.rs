@@ -0,0 +1,27 @@
+//! Writes a generated puzzle input to standard output, or to the file given by `--output`.
+//!
+//! Usage: `generate --day 1 [--size 1000000] [--difficulty 0.5] [--seed 42] [--output path]`
+
+use aoc2024::cli;
+use aoc2024::generate;
+use aoc2024::random::Rng;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+fn main() -> io::Result<()> {
+    let day: usize = cli::parsed_option("--day").expect("--day should be given");
+    let defaults = generate::Options::default();
+    let options = generate::Options {
+        size: cli::parsed_option("--size").unwrap_or(defaults.size),
+        difficulty: cli::parsed_option("--difficulty").unwrap_or(defaults.difficulty),
+    };
+    let mut rng = Rng::new(cli::parsed_option("--seed").unwrap_or(0));
+
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match cli::option("--output") {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout().lock()),
+    });
+
+    generate::generate(day, &mut rng, options, &mut writer)?;
+    writer.flush()
+}
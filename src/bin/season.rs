@@ -0,0 +1,154 @@
+//! Runs every day and reports each day's share of a time budget for the whole season.
+//!
+//! The day binaries are expected next to this one, so build them first with `cargo build --bins`
+//! (or `--release`). Pass `--budget DURATION` to change the season budget from one second.
+//!
+//! Days run concurrently on one worker per CPU, or `--workers N`. Every day times its own solver,
+//! but concurrent days compete for the CPUs, so use `--sequential` for clean benchmarks.
+
+use aoc2024::budget::{self, Report};
+use aoc2024::cli;
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DAYS: &[usize] = &[1, 2, 3, 4, 5, 6, 7];
+
+/// The total runtime a day binary reported, if it printed one.
+fn runtime(output: &str) -> Option<Duration> {
+    let line = output.lines().find_map(|l| l.strip_prefix("Runtime: "))?;
+    budget::parse_duration(line.split_whitespace().next()?).ok()
+}
+
+fn day_binary(day: usize) -> PathBuf {
+    let current = std::env::current_exe().expect("path of the current binary should be known");
+    current.with_file_name(format!("day{day:02}{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Runs `job` for every item on a pool of `workers` threads, returning the results in the order
+/// of the items.
+fn pool<T: Send>(items: &[usize], workers: usize, job: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+
+    thread::scope(|s| {
+        for _ in 0..workers.clamp(1, items.len().max(1)) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&item) = items.get(i) else {
+                    break;
+                };
+                let result = job(item);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every item should have been run"))
+        .collect()
+}
+
+fn main() -> ExitCode {
+    let budget = cli::option("--budget").map_or(Duration::from_secs(1), |v| {
+        budget::parse_duration(&v)
+            .unwrap_or_else(|e| panic!("invalid value {v:?} for --budget: {e}"))
+    });
+    let workers = if cli::flag("--sequential") {
+        1
+    } else {
+        cli::parsed_option("--workers")
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+    };
+
+    let start = Instant::now();
+    let outputs = pool(DAYS, workers, |day| {
+        let binary = day_binary(day);
+        // No part of a single day may take longer than the whole season.
+        Command::new(&binary)
+            .arg(format!("--budget={budget:?}"))
+            .output()
+            .unwrap_or_else(|e| panic!("failed to run {}: {e}", binary.display()))
+    });
+    let elapsed = start.elapsed();
+
+    let mut report = Report::new(budget);
+    let mut failed = vec![];
+
+    for (&day, output) in DAYS.iter().zip(outputs) {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        println!("Day {day}");
+        print!("{stdout}");
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+
+        // A day that crashed or ran out of time uses up the whole budget.
+        match runtime(&stdout) {
+            Some(runtime) => report.add(day, runtime),
+            None => report.add(day, budget),
+        }
+        // Day binaries fail on incorrect answers as well as on crashes and exceeded budgets.
+        if !output.status.success() {
+            failed.push(day.to_string());
+        }
+    }
+
+    println!();
+    print!("{report}");
+    println!("Wall time: {elapsed:?} with {workers} worker(s)");
+
+    if !failed.is_empty() {
+        println!("Failed days: {}", failed.join(", "));
+    }
+
+    if !failed.is_empty() || !report.is_within_budget() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{pool, runtime};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_runtime() {
+        let output = "Getting day 1 input from cache\n\
+                      Part 1: 11 (CORRECT)\n\
+                      Part 2: 31 (CORRECT)\n\
+                      Runtime: 1.5ms (part 1: 1ms, part 2: 500µs)\n";
+        assert_eq!(runtime(output), Some(Duration::from_micros(1500)));
+        assert_eq!(
+            runtime("Day 6 part 2 did not finish within the budget of 1s"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pool() {
+        let items = (0..20).collect::<Vec<_>>();
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+
+        let results = pool(&items, 3, |i| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            most.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+            i * i
+        });
+
+        assert_eq!(results, items.iter().map(|i| i * i).collect::<Vec<_>>());
+        assert!(most.load(Ordering::SeqCst) <= 3);
+        assert_eq!(pool(&[], 4, |i| i), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,235 @@
+//! Time budgets for solvers.
+//!
+//! A solver runs on its own thread while the calling thread keeps watch, so a solver that takes
+//! far too long is reported as soon as its budget runs out instead of when it finally finishes.
+
+use std::fmt::{self, Display, Formatter};
+use std::panic;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Budgets for the two parts of a day, each of which may be unlimited.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Budgets {
+    pub part1: Option<Duration>,
+    pub part2: Option<Duration>,
+}
+
+impl Budgets {
+    pub const NONE: Self = Self {
+        part1: None,
+        part2: None,
+    };
+
+    /// The same budget for both parts.
+    pub fn both(budget: Duration) -> Self {
+        Self {
+            part1: Some(budget),
+            part2: Some(budget),
+        }
+    }
+}
+
+/// A solver that did not finish within its budget.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Exceeded {
+    pub budget: Duration,
+    /// How long the solver took, if it finished at all.
+    pub elapsed: Option<Duration>,
+}
+
+impl Display for Exceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.elapsed {
+            Some(elapsed) => write!(f, "took {elapsed:?}, over the budget of {:?}", self.budget),
+            None => write!(f, "did not finish within the budget of {:?}", self.budget),
+        }
+    }
+}
+
+/// Runs `f` on a new thread, returning its result and runtime if it finishes within `budget`.
+///
+/// When the budget is exceeded the thread is left running in the background.
+pub fn run<T, F>(budget: Duration, f: F) -> Result<(T, Duration), Exceeded>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let handle = thread::spawn(move || timed(f, sender));
+    wait(receiver, budget, || handle.join())
+}
+
+fn timed<T>(f: impl FnOnce() -> T, sender: mpsc::Sender<(T, Duration)>) {
+    let start = Instant::now();
+    let value = f();
+    // The receiver is gone if the budget ran out, in which case nobody wants the result.
+    let _ = sender.send((value, start.elapsed()));
+}
+
+fn wait<T>(
+    receiver: Receiver<(T, Duration)>,
+    budget: Duration,
+    join: impl FnOnce() -> thread::Result<()>,
+) -> Result<(T, Duration), Exceeded> {
+    match receiver.recv_timeout(budget) {
+        Ok((_, elapsed)) if elapsed > budget => Err(Exceeded {
+            budget,
+            elapsed: Some(elapsed),
+        }),
+        Ok(result) => Ok(result),
+        Err(RecvTimeoutError::Timeout) => Err(Exceeded {
+            budget,
+            elapsed: None,
+        }),
+        Err(RecvTimeoutError::Disconnected) => match join() {
+            Err(payload) => panic::resume_unwind(payload),
+            Ok(()) => unreachable!("the solver thread should send its result before finishing"),
+        },
+    }
+}
+
+/// Parses durations as printed by their `Debug` implementation, such as `1.5s` or `250µs`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+
+    let value = value
+        .parse::<f64>()
+        .map_err(|_| format!("invalid duration {text:?}"))?;
+    let scale = match unit {
+        "ns" => 1e-9,
+        "µs" | "us" => 1e-6,
+        "ms" => 1e-3,
+        "s" => 1.0,
+        _ => return Err(format!("unknown unit {unit:?} in duration {text:?}")),
+    };
+
+    Duration::try_from_secs_f64(value * scale)
+        .map_err(|e| format!("invalid duration {text:?}: {e}"))
+}
+
+/// Every day's runtime as a share of a budget for the whole season.
+#[derive(Debug, Clone)]
+pub struct Report {
+    budget: Duration,
+    days: Vec<(usize, Duration)>,
+}
+
+impl Report {
+    pub fn new(budget: Duration) -> Self {
+        Self {
+            budget,
+            days: vec![],
+        }
+    }
+
+    pub fn add(&mut self, day: usize, runtime: Duration) {
+        self.days.push((day, runtime));
+    }
+
+    pub fn total(&self) -> Duration {
+        self.days.iter().map(|&(_, runtime)| runtime).sum()
+    }
+
+    pub fn is_within_budget(&self) -> bool {
+        self.total() <= self.budget
+    }
+
+    fn share(&self, runtime: Duration) -> f64 {
+        100.0 * runtime.as_secs_f64() / self.budget.as_secs_f64()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for &(day, runtime) in &self.days {
+            let share = self.share(runtime);
+            let runtime = format!("{runtime:?}");
+            writeln!(f, "Day {day:>2}: {runtime:>12} {share:>6.2}%")?;
+        }
+
+        let total = self.total();
+        writeln!(
+            f,
+            "Total:  {:>12} {:>6.2}% of {:?}, {}",
+            format!("{total:?}"),
+            self.share(total),
+            self.budget,
+            if self.is_within_budget() {
+                "within budget"
+            } else {
+                "over budget"
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_duration, run, Exceeded, Report};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run() {
+        let (value, elapsed) = run(Duration::from_secs(10), || 6 * 7).unwrap();
+        assert_eq!(value, 42);
+        assert!(elapsed < Duration::from_secs(10));
+
+        let budget = Duration::from_millis(20);
+        assert_eq!(
+            run(budget, || thread::sleep(Duration::from_secs(10))),
+            Err(Exceeded {
+                budget,
+                elapsed: None
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "solver failed")]
+    fn test_run_panics() {
+        let _ = run(Duration::from_secs(10), || panic!("solver failed"));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        for duration in [
+            Duration::ZERO,
+            Duration::from_nanos(17),
+            Duration::from_micros(250),
+            Duration::from_nanos(1_234_567),
+            Duration::from_millis(1500),
+        ] {
+            let parsed = parse_duration(&format!("{duration:?}")).unwrap();
+            assert!(parsed.abs_diff(duration) < Duration::from_nanos(2));
+        }
+
+        assert_eq!(parse_duration("2us"), Ok(Duration::from_micros(2)));
+        assert!(parse_duration("2").is_err());
+        assert!(parse_duration("ms").is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = Report::new(Duration::from_secs(1));
+        report.add(1, Duration::from_millis(100));
+        report.add(6, Duration::from_millis(650));
+        assert!(report.is_within_budget());
+        assert_eq!(
+            report.to_string(),
+            "Day  1:        100ms  10.00%\n\
+             Day  6:        650ms  65.00%\n\
+             Total:         750ms  75.00% of 1s, within budget\n"
+        );
+
+        report.add(7, Duration::from_millis(300));
+        assert!(!report.is_within_budget());
+        assert!(report.to_string().ends_with("105.00% of 1s, over budget\n"));
+    }
+}
@@ -0,0 +1,34 @@
+//! Minimal command line handling for the day binaries.
+
+/// Whether `--name` was passed on the command line.
+pub fn flag(name: &str) -> bool {
+    std::env::args().skip(1).any(|a| a == name)
+}
+
+/// The value passed as `--name value` or `--name=value`, if any.
+pub fn option(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+
+        if let Some(value) = arg.strip_prefix(name).and_then(|r| r.strip_prefix('=')) {
+            return Some(value.to_owned());
+        }
+    }
+
+    None
+}
+
+/// Parses the value of `--name`, panicking with a readable message if it is invalid.
+pub fn parsed_option<T: std::str::FromStr>(name: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    option(name).map(|v| {
+        v.parse()
+            .unwrap_or_else(|e| panic!("invalid value {v:?} for {name}: {e}"))
+    })
+}
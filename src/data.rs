@@ -3,14 +3,24 @@ fn get_session() -> String {
     std::env::var("AOC_SESSION").expect("environment variable AOC_SESSION should be set")
 }
 
-pub fn get_day_input(day: usize) -> String {
+pub fn get_cached_day_input(day: usize) -> Option<String> {
     let file_name = format!("data/{day:02}.in");
 
-    if std::fs::exists(&file_name).unwrap() {
+    if !std::fs::exists(&file_name).unwrap() {
+        return None;
+    }
+
+    Some(std::fs::read_to_string(&file_name).unwrap())
+}
+
+pub fn get_day_input(day: usize) -> String {
+    if let Some(content) = get_cached_day_input(day) {
         println!("Getting day {day} input from cache");
-        return std::fs::read_to_string(&file_name).unwrap();
+        return content;
     }
 
+    let file_name = format!("data/{day:02}.in");
+
     println!("Fetching day {day} input from AOC");
 
     let session = get_session();
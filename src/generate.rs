@@ -0,0 +1,354 @@
+//! Seeded generators of puzzle inputs, for benchmarks and tests.
+//!
+//! Generators write straight to a writer, so they can produce inputs far larger than memory. Every
+//! generator takes the same [Options]; what `size` and `difficulty` mean for a day is documented on
+//! its generator.
+
+use crate::random::Rng;
+use std::collections::HashSet;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Options {
+    pub size: usize,
+    /// Between 0 and 1, where higher values make inputs harder to solve.
+    pub difficulty: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            size: 1000,
+            difficulty: 0.5,
+        }
+    }
+}
+
+/// Writes an input for `day`, failing if there is no generator for it.
+pub fn generate(
+    day: usize,
+    rng: &mut Rng,
+    options: Options,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    match day {
+        1 => day01(rng, options, writer),
+        2 => day02(rng, options, writer),
+        3 => day03(rng, options, writer),
+        4 => day04(rng, options, writer),
+        5 => day05(rng, options, writer),
+        6 => day06(rng, options, writer),
+        7 => day07(rng, options, writer),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no generator for day {day}"),
+        )),
+    }
+}
+
+/// Generates an input for `day` in memory, panicking if there is no generator for it.
+pub fn input(day: usize, seed: u64, options: Options) -> String {
+    let mut output = vec![];
+    generate(day, &mut Rng::new(seed), options, &mut output).unwrap();
+    String::from_utf8(output).expect("generated inputs should be ASCII")
+}
+
+/// Writes `size` pairs of location IDs. `difficulty` is the share of right IDs that repeat a
+/// recent left ID, which makes the similarity score larger.
+pub fn day01(rng: &mut Rng, options: Options, writer: &mut impl Write) -> io::Result<()> {
+    let mut recent = Vec::with_capacity(64);
+
+    for _ in 0..options.size {
+        let left = rng.range(10_000..100_000u32);
+        let right = if !recent.is_empty() && rng.chance(options.difficulty) {
+            *rng.choose(&recent)
+        } else {
+            rng.range(10_000..100_000)
+        };
+
+        if recent.len() < 64 {
+            recent.push(left);
+        } else {
+            recent[rng.range(0..64)] = left;
+        }
+
+        writeln!(writer, "{left}   {right}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes `size` reports of 5 to 8 levels. `difficulty` is the chance that a report has faults:
+/// equal levels, steps that are too large or changes of direction.
+pub fn day02(rng: &mut Rng, options: Options, writer: &mut impl Write) -> io::Result<()> {
+    for _ in 0..options.size {
+        let length = rng.range(5..=8);
+        let increasing = rng.chance(0.5);
+        let mut levels = vec![rng.range(30..=90i64)];
+        for _ in 1..length {
+            let step = rng.range(1..=3);
+            let last = *levels.last().unwrap();
+            levels.push(if increasing { last + step } else { last - step });
+        }
+
+        if rng.chance(options.difficulty) {
+            for _ in 0..rng.range(1..=2) {
+                let i = rng.range(1..length);
+                levels[i] = match rng.range(0..3) {
+                    0 => levels[i - 1],
+                    1 => levels[i - 1] + rng.range(4..=9) * if increasing { 1 } else { -1 },
+                    _ => levels[i - 1] + rng.range(1..=3) * if increasing { -1 } else { 1 },
+                };
+            }
+        }
+
+        let levels = levels.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        writeln!(writer, "{}", levels.join(" "))?;
+    }
+
+    Ok(())
+}
+
+/// Writes `size` fragments of corrupted memory, each an instruction or noise. `difficulty` is the
+/// chance that a part of an instruction is corrupted into something almost valid.
+pub fn day03(rng: &mut Rng, options: Options, writer: &mut impl Write) -> io::Result<()> {
+    const NOISE: &[&str] = &[
+        "mul", "(", ")", ",", "+", "-", " ", "[", "]", "!", "@", "what()", "select()", "mul[",
+    ];
+
+    let mut fragment = String::new();
+    for _ in 0..options.size {
+        fragment.clear();
+
+        match rng.range(0..10) {
+            0 => fragment += "do()",
+            1 => fragment += "don't()",
+            2..=3 => fragment += *rng.choose(NOISE),
+            _ => {
+                for part in ["mul(", "", ",", "", ")"] {
+                    if rng.chance(options.difficulty / 4.0) {
+                        fragment += *rng.choose(NOISE);
+                    } else if part.is_empty() {
+                        let digits = if rng.chance(options.difficulty / 4.0) {
+                            4
+                        } else {
+                            rng.range(1..=3)
+                        };
+                        for _ in 0..digits {
+                            fragment.push(char::from(b'0' + rng.range(0..10u8)));
+                        }
+                    } else {
+                        fragment += part;
+                    }
+                }
+            }
+        }
+
+        writer.write_all(fragment.as_bytes())?;
+    }
+
+    writeln!(writer)
+}
+
+/// Writes a `size` by `size` grid of letters. `difficulty` is the chance that a letter is one of
+/// `XMAS` rather than any other letter, which makes matches denser.
+pub fn day04(rng: &mut Rng, options: Options, writer: &mut impl Write) -> io::Result<()> {
+    let mut row = vec![0; options.size];
+
+    for _ in 0..options.size {
+        for letter in row.iter_mut() {
+            *letter = if rng.chance(options.difficulty) {
+                *rng.choose(b"XMAS")
+            } else {
+                rng.range(b'A'..=b'Z')
+            };
+        }
+        writer.write_all(&row)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes rules ordering every pair of 49 pages and `size` updates of 5 to 23 pages.
+/// `difficulty` is the chance that an update is out of order.
+pub fn day05(rng: &mut Rng, options: Options, writer: &mut impl Write) -> io::Result<()> {
+    let mut pages = (10..100).collect::<Vec<u32>>();
+    rng.shuffle(&mut pages);
+    pages.truncate(49);
+
+    let mut rules = (0..pages.len())
+        .flat_map(|i| (i + 1..pages.len()).map(move |j| (i, j)))
+        .collect::<Vec<_>>();
+    rng.shuffle(&mut rules);
+    for (i, j) in rules {
+        writeln!(writer, "{}|{}", pages[i], pages[j])?;
+    }
+    writeln!(writer)?;
+
+    let mut indices = (0..pages.len()).collect::<Vec<_>>();
+    for _ in 0..options.size {
+        rng.shuffle(&mut indices);
+        let update = &mut indices[..rng.range(2..=11) * 2 + 1];
+        if rng.chance(options.difficulty) {
+            // The shuffled pages may happen to be in order, so make sure two pages are swapped.
+            if update.is_sorted() {
+                update.swap(0, 1);
+            }
+        } else {
+            update.sort_unstable();
+        }
+
+        let update = update
+            .iter()
+            .map(|&i| pages[i].to_string())
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", update.join(","))?;
+    }
+
+    Ok(())
+}
+
+/// Writes a `size` by `size` lab map in which the guard eventually leaves the lab. `difficulty`
+/// scales the density of obstructions up to one cell in five.
+pub fn day06(rng: &mut Rng, options: Options, writer: &mut impl Write) -> io::Result<()> {
+    let size = options.size.max(1);
+    let density = options.difficulty * 0.2;
+
+    let (map, start) = loop {
+        let map = (0..size * size)
+            .map(|_| rng.chance(density))
+            .collect::<Vec<_>>();
+        let start = rng.range(0..size * size);
+        if !map[start] && guard_leaves(&map, size, start) {
+            break (map, start);
+        }
+    };
+
+    for r in 0..size {
+        let row = (0..size)
+            .map(|c| match r * size + c {
+                i if i == start => b'^',
+                i if map[i] => b'#',
+                _ => b'.',
+            })
+            .collect::<Vec<_>>();
+        writer.write_all(&row)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Whether a guard starting upwards from `start` walks out of the map instead of looping.
+fn guard_leaves(map: &[bool], size: usize, start: usize) -> bool {
+    let (mut r, mut c) = ((start / size) as i64, (start % size) as i64);
+    let (mut dr, mut dc) = (-1, 0);
+    let mut seen = HashSet::new();
+
+    while seen.insert((r, c, dr, dc)) {
+        let (nr, nc) = (r + dr, c + dc);
+        if !(0..size as i64).contains(&nr) || !(0..size as i64).contains(&nc) {
+            return true;
+        }
+
+        if map[nr as usize * size + nc as usize] {
+            (dr, dc) = (dc, -dr);
+        } else {
+            (r, c) = (nr, nc);
+        }
+    }
+
+    false
+}
+
+/// Writes `size` equations of operands below 1000, including the occasional zero. `difficulty`
+/// scales the number of operands from 2 up to 12; about half of the equations can be satisfied
+/// with additions, multiplications and concatenations.
+pub fn day07(rng: &mut Rng, options: Options, writer: &mut impl Write) -> io::Result<()> {
+    let max_operands = 2 + (options.difficulty * 10.0).round() as usize;
+
+    for _ in 0..options.size {
+        let (expected, numbers) = loop {
+            let numbers = (0..rng.range(2..=max_operands))
+                .map(|_| rng.range(0..1000i64))
+                .collect::<Vec<_>>();
+
+            let expected = if rng.chance(0.5) {
+                numbers[1..]
+                    .iter()
+                    .try_fold(numbers[0], |value, &n| match rng.range(0..3) {
+                        0 => value.checked_add(n),
+                        1 => value.checked_mul(n),
+                        _ => crate::numeric::checked_concat(value, n),
+                    })
+            } else {
+                Some(rng.range(1..1_000_000_000_000i64))
+            };
+
+            if let Some(expected) = expected {
+                break (expected, numbers);
+            }
+        };
+
+        let numbers = numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        writeln!(writer, "{expected}: {}", numbers.join(" "))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{guard_leaves, input, Options};
+
+    #[test]
+    fn test_deterministic() {
+        let options = Options {
+            size: 20,
+            difficulty: 0.5,
+        };
+
+        for day in 1..=7 {
+            let generated = input(day, 1, options);
+            assert!(!generated.is_empty(), "day {day} generated nothing");
+            assert_eq!(generated, input(day, 1, options), "day {day} is not seeded");
+            assert_ne!(
+                generated,
+                input(day, 2, options),
+                "day {day} ignores the seed"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sizes() {
+        let options = Options {
+            size: 30,
+            difficulty: 1.0,
+        };
+
+        assert_eq!(input(1, 0, options).lines().count(), 30);
+        assert_eq!(input(2, 0, options).lines().count(), 30);
+        assert_eq!(input(4, 0, options).lines().count(), 30);
+        assert_eq!(input(5, 0, options).lines().count(), 49 * 48 / 2 + 1 + 30);
+        assert_eq!(input(7, 0, options).lines().count(), 30);
+
+        let map = input(6, 0, options);
+        assert_eq!(map.lines().count(), 30);
+        assert_eq!(map.matches('^').count(), 1);
+    }
+
+    #[test]
+    fn test_guard_leaves() {
+        assert!(guard_leaves(&[false; 9], 3, 4));
+        // The guard turns right at every obstruction and walks around the centre forever.
+        let map = [
+            false, true, false, false, //
+            false, false, false, true, //
+            true, false, false, false, //
+            false, false, true, false, //
+        ];
+        assert!(!guard_leaves(&map, 4, 5));
+    }
+}
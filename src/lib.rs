@@ -1,32 +1,79 @@
 pub mod algorithms;
 mod answers;
+pub mod budget;
+pub mod cli;
 mod data;
+pub mod generate;
+pub mod numeric;
 pub mod parsing;
+pub mod random;
+pub mod render;
+pub mod testing;
+pub mod vm;
 
+pub use budget::Budgets;
+
+use std::any::Any;
 use std::fmt::{Debug, Display};
-use std::process::{ExitCode, Termination};
-use std::time::Instant;
+use std::panic::{self, AssertUnwindSafe, RefUnwindSafe};
+use std::process::{self, ExitCode, Termination};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-pub fn execute_day<F, A1, A2>(day: usize, solver: F) -> impl Termination
+/// Solves both parts of the day's input and prints the answers, failing if any of them is
+/// incorrect. Each part solves the input on its own, so that the parts are timed separately.
+pub fn execute_day<P1, P2, A1, A2>(day: usize, part1: P1, part2: P2) -> impl Termination
 where
-    F: FnOnce(&str) -> (A1, A2),
-    A1: Display,
-    A2: Display,
+    P1: FnOnce(&str) -> A1 + Send + 'static,
+    P2: FnOnce(&str) -> A2 + Send + 'static,
+    A1: Display + Send + 'static,
+    A2: Display + Send + 'static,
 {
-    let input = data::get_day_input(day);
-    let start = Instant::now();
-    let answers = solver(&input);
-    let elapsed = start.elapsed();
+    execute_day_within(day, Budgets::NONE, part1, part2)
+}
 
-    let answers = (answers.0, answers.1);
+/// Like [execute_day], but fails as soon as a part runs for longer than its budget.
+///
+/// With `--budget DURATION`, such as `--budget 1s`, both parts get that budget instead.
+pub fn execute_day_within<P1, P2, A1, A2>(
+    day: usize,
+    budgets: Budgets,
+    part1: P1,
+    part2: P2,
+) -> impl Termination
+where
+    P1: FnOnce(&str) -> A1 + Send + 'static,
+    P2: FnOnce(&str) -> A2 + Send + 'static,
+    A1: Display + Send + 'static,
+    A2: Display + Send + 'static,
+{
+    let budgets = cli::option("--budget").map_or(budgets, |v| {
+        budget::parse_duration(&v)
+            .map(Budgets::both)
+            .unwrap_or_else(|e| panic!("invalid value {v:?} for --budget: {e}"))
+    });
 
-    let (answer1, answer2) = answers::validate_answers(day, answers);
+    let input = Arc::<str>::from(data::get_day_input(day));
+    let exit_if_exceeded = |part: usize, e: budget::Exceeded| -> ! {
+        eprintln!("Day {day} part {part} {e}");
+        // The part is still running, and exiting is the only way to stop it.
+        process::exit(1)
+    };
+    let (answer1, elapsed1) =
+        solve_part(&input, budgets.part1, part1).unwrap_or_else(|e| exit_if_exceeded(1, e));
+    let (answer2, elapsed2) =
+        solve_part(&input, budgets.part2, part2).unwrap_or_else(|e| exit_if_exceeded(2, e));
+
+    let (answer1, answer2) = answers::validate_answers(day, (answer1, answer2));
 
     println!("{}", answer1);
     println!("{}", answer2);
-    println!("Runtime: {elapsed:?}");
+    println!(
+        "Runtime: {:?} (part 1: {elapsed1:?}, part 2: {elapsed2:?})",
+        elapsed1 + elapsed2
+    );
 
-    if std::env::args().any(|a| a == "--save-output") {
+    if cli::flag("--save-output") {
         data::set_day_output(day, &answer1.value, &answer2.value);
     }
 
@@ -37,16 +84,476 @@ where
     }
 }
 
-pub fn test_day<F, T1, T2>(solver: F, input: &str, (expected1, expected2): (T1, Option<T2>))
+/// The day's input, downloading it first if it is not cached yet.
+pub fn day_input(day: usize) -> String {
+    data::get_day_input(day)
+}
+
+/// Solves a part and times it, on a separate thread if it has a budget.
+fn solve_part<P, A>(
+    input: &Arc<str>,
+    budget: Option<Duration>,
+    part: P,
+) -> Result<(A, Duration), budget::Exceeded>
 where
-    F: FnOnce(&str) -> (T1, T2),
+    P: FnOnce(&str) -> A + Send + 'static,
+    A: Send + 'static,
+{
+    let Some(budget) = budget else {
+        let start = Instant::now();
+        let answer = part(input);
+        return Ok((answer, start.elapsed()));
+    };
+
+    let input = Arc::clone(input);
+    budget::run(budget, move || part(&input))
+}
+
+pub fn test_day<P1, P2, T1, T2>(
+    part1: P1,
+    part2: P2,
+    input: &str,
+    (expected1, expected2): (T1, Option<T2>),
+) where
+    P1: Fn(&str) -> T1,
+    P2: Fn(&str) -> T2,
     T1: PartialEq + Debug,
     T2: PartialEq + Debug,
 {
-    let (answer1, answer2) = solver(input);
-    assert_eq!(answer1, expected1, "Part 1 failed");
+    DayTest::new(part1, part2)
+        .case("example", input, Some(expected1), expected2)
+        .run();
+}
+
+/// Like [test_day], but also fails if a part runs for longer than its budget.
+pub fn test_day_within<P1, P2, T1, T2>(
+    part1: P1,
+    part2: P2,
+    input: &str,
+    (expected1, expected2): (T1, Option<T2>),
+    budgets: Budgets,
+) where
+    P1: Fn(&str) -> T1 + Clone + Send + 'static,
+    P2: Fn(&str) -> T2 + Clone + Send + 'static,
+    T1: PartialEq + Debug + Send + 'static,
+    T2: PartialEq + Debug + Send + 'static,
+{
+    DayTest::new(part1, part2)
+        .case("example", input, Some(expected1), expected2)
+        .within(budgets)
+        .run();
+}
+
+/// Solves the real input of `day` within `budgets`, checking the answers against the saved ones.
+///
+/// Inputs are personal and not checked in, so this does nothing but print a note if the input has
+/// not been downloaded yet.
+pub fn test_real_input_within<P1, P2, A1, A2>(day: usize, budgets: Budgets, part1: P1, part2: P2)
+where
+    P1: FnOnce(&str) -> A1 + Send + 'static,
+    P2: FnOnce(&str) -> A2 + Send + 'static,
+    A1: Display + Send + 'static,
+    A2: Display + Send + 'static,
+{
+    let Some(input) = data::get_cached_day_input(day) else {
+        println!("Skipping day {day} on the real input, which has not been downloaded");
+        return;
+    };
+
+    let input = Arc::<str>::from(input);
+    let exceeded = |part: usize, e: budget::Exceeded| -> ! {
+        panic!("day {day} part {part} {e} on the real input")
+    };
+    let (answer1, _) = solve_part(&input, budgets.part1, part1).unwrap_or_else(|e| exceeded(1, e));
+    let (answer2, _) = solve_part(&input, budgets.part2, part2).unwrap_or_else(|e| exceeded(2, e));
+
+    for answer in <[_; 2]>::from(answers::validate_answers(day, (answer1, answer2))) {
+        assert!(
+            !answer.is_incorrect(),
+            "day {day} on the real input: {answer}"
+        );
+    }
+}
+
+/// Checks the two parts of a solution against any number of named example inputs, each with the
+/// expected answer of one or both parts. Only the parts with an expected answer are run.
+///
+/// Every case is run even if an earlier one fails, and [DayTest::run] then reports all failures
+/// together, each with its input and a diff of the expected and actual answers.
+pub struct DayTest<'a, P1, P2, T1, T2> {
+    part1: P1,
+    part2: P2,
+    cases: Vec<Case<'a, T1, T2>>,
+}
+
+struct Case<'a, T1, T2> {
+    name: String,
+    input: &'a str,
+    expected1: Option<T1>,
+    expected2: Option<T2>,
+}
+
+impl<'a, P1, P2, T1, T2> DayTest<'a, P1, P2, T1, T2>
+where
+    P1: Fn(&str) -> T1,
+    P2: Fn(&str) -> T2,
+    T1: PartialEq + Debug,
+    T2: PartialEq + Debug,
+{
+    pub fn new(part1: P1, part2: P2) -> Self {
+        Self {
+            part1,
+            part2,
+            cases: vec![],
+        }
+    }
+
+    fn case(
+        mut self,
+        name: &str,
+        input: &'a str,
+        expected1: Option<T1>,
+        expected2: Option<T2>,
+    ) -> Self {
+        self.cases.push(Case {
+            name: name.to_owned(),
+            input,
+            expected1,
+            expected2,
+        });
+        self
+    }
+
+    /// Adds a case checking only the answer of part 1.
+    pub fn part1(self, input: &'a str, expected: T1) -> Self {
+        let name = format!("part 1 example {}", self.cases.len() + 1);
+        self.case(&name, input, Some(expected), None)
+    }
+
+    /// Adds a case checking only the answer of part 2.
+    pub fn part2(self, input: &'a str, expected: T2) -> Self {
+        let name = format!("part 2 example {}", self.cases.len() + 1);
+        self.case(&name, input, None, Some(expected))
+    }
+
+    /// Names the case added last, for failure messages.
+    pub fn named(mut self, name: &str) -> Self {
+        let case = self.cases.last_mut().expect("a case should be added first");
+        case.name = name.to_owned();
+        self
+    }
+
+    /// Fails every case on which a part runs for longer than its budget.
+    pub fn within(
+        self,
+        budgets: Budgets,
+    ) -> DayTest<'a, impl Fn(&str) -> T1, impl Fn(&str) -> T2, T1, T2>
+    where
+        P1: Clone + Send + 'static,
+        P2: Clone + Send + 'static,
+        T1: Send + 'static,
+        T2: Send + 'static,
+    {
+        DayTest {
+            part1: within(1, budgets.part1, self.part1),
+            part2: within(2, budgets.part2, self.part2),
+            cases: self.cases,
+        }
+    }
+
+    /// Runs every case, panicking with a report of all failing ones.
+    pub fn run(self) {
+        let mut failures = vec![];
+
+        for case in &self.cases {
+            let problems = [
+                check(1, &self.part1, case.input, case.expected1.as_ref()),
+                check(2, &self.part2, case.input, case.expected2.as_ref()),
+            ];
+            let problems = problems.into_iter().flatten().collect::<Vec<_>>();
+
+            if !problems.is_empty() {
+                failures.push(format!(
+                    "{}:\n{}\ninput:\n{}",
+                    case.name,
+                    problems.join("\n"),
+                    case.input.trim_end()
+                ));
+            }
+        }
+
+        if !failures.is_empty() {
+            panic!(
+                "{} of {} cases failed\n\n{}",
+                failures.len(),
+                self.cases.len(),
+                failures.join("\n\n")
+            );
+        }
+    }
+}
+
+/// Wraps a part so that it panics if it runs for longer than `budget`.
+fn within<P, T>(part: usize, budget: Option<Duration>, solve: P) -> impl Fn(&str) -> T
+where
+    P: Fn(&str) -> T + Clone + Send + 'static,
+    T: Send + 'static,
+{
+    move |input| {
+        let Some(budget) = budget else {
+            return solve(input);
+        };
+
+        let (solve, input) = (solve.clone(), input.to_owned());
+        match budget::run(budget, move || solve(&input)) {
+            Ok((answer, _)) => answer,
+            Err(e) => panic!("part {part} {e}"),
+        }
+    }
+}
+
+/// Runs a part if its answer is expected to be anything, describing how it failed if it did.
+fn check<T: PartialEq + Debug>(
+    part: usize,
+    solve: impl Fn(&str) -> T,
+    input: &str,
+    expected: Option<&T>,
+) -> Option<String> {
+    let expected = expected?;
+    match panic::catch_unwind(AssertUnwindSafe(|| solve(input))) {
+        Ok(actual) => compare(part, expected, &actual),
+        Err(payload) => Some(format!(
+            "part {part} panicked: {}",
+            panic_message(&*payload)
+        )),
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "no message".to_owned()
+    }
+}
+
+/// Describes how an answer differs from the expected one, if it does.
+fn compare<T: PartialEq + Debug>(part: usize, expected: &T, actual: &T) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    Some(format!(
+        "part {part} failed:\n{}",
+        diff(&format!("{expected:#?}"), &format!("{actual:#?}"))
+    ))
+}
+
+/// A line diff of two texts, with lines only in `expected` marked `-` and lines only in `actual`
+/// marked `+`.
+fn diff(expected: &str, actual: &str) -> String {
+    let (expected, actual) = (
+        expected.lines().collect::<Vec<_>>(),
+        actual.lines().collect::<Vec<_>>(),
+    );
+
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..].
+    let mut common = vec![vec![0; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!("  {}", expected[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1])
+        {
+            lines.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Compares `solver` against a slow but obviously correct `reference` on `cases` inputs generated
+/// for `day`, shrinking the first mismatch to a minimal input by deleting lines.
+///
+/// Inputs on which the reference panics are considered invalid, so a reference should reject
+/// inputs the puzzle does not allow before the fast solver gets to run on them.
+pub fn differential_test_day<F, R, T1, T2>(
+    day: usize,
+    solver: F,
+    reference: R,
+    options: generate::Options,
+    cases: u64,
+) where
+    F: Fn(&str) -> (T1, T2) + RefUnwindSafe,
+    R: Fn(&str) -> (T1, T2) + RefUnwindSafe,
+    T1: PartialEq + Debug,
+    T2: PartialEq + Debug,
+{
+    let compare = |input: &str| {
+        let expected = panic::catch_unwind(|| reference(input)).ok()?;
+        let actual = panic::catch_unwind(|| solver(input));
+        let matches = actual.as_ref().is_ok_and(|a| *a == expected);
+        Some((expected, actual, matches))
+    };
+    let fails = |input: &str| compare(input).is_some_and(|(_, _, matches)| !matches);
+
+    for seed in 0..cases {
+        let input = generate::input(day, seed, options);
+        let (_, _, matches) = compare(&input)
+            .unwrap_or_else(|| panic!("reference solver rejected generated input {seed}"));
+
+        if !matches {
+            let input = shrink(&input, fails);
+            let (expected, actual, _) = compare(&input).unwrap();
+            let actual = match actual {
+                Ok(actual) => format!("{actual:?}"),
+                Err(_) => "a panic".to_owned(),
+            };
+            panic!(
+                "solver differs from reference on generated input {seed}, \
+                 shrunk to:\n{input}\nexpected {expected:?}, got {actual}"
+            );
+        }
+    }
+}
+
+/// Deletes ever smaller chunks of lines from the input as long as it keeps failing.
+fn shrink(input: &str, fails: impl Fn(&str) -> bool) -> String {
+    let mut lines = input.lines().collect::<Vec<_>>();
+    let mut chunk = lines.len().div_ceil(2).max(1);
+
+    loop {
+        let mut start = 0;
+        while start < lines.len() && lines.len() > 1 {
+            let end = (start + chunk).min(lines.len());
+            let candidate = [&lines[..start], &lines[end..]].concat();
+            if fails(&candidate.join("\n")) {
+                lines = candidate;
+            } else {
+                start = end;
+            }
+        }
+
+        if chunk == 1 {
+            return lines.join("\n");
+        }
+        chunk = chunk.div_ceil(2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{diff, differential_test_day, generate, shrink, Budgets, DayTest};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_shrink() {
+        let input = (1..=50)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let fails =
+            |input: &str| input.lines().any(|l| l == "17") && input.lines().any(|l| l == "40");
+
+        assert_eq!(shrink(&input, fails), "17\n40");
+        assert_eq!(shrink("1\n2", |_| true), "2");
+    }
+
+    fn count_lines(input: &str) -> (usize, usize) {
+        (input.lines().count(), 0)
+    }
+
+    #[test]
+    fn test_differential_passes() {
+        differential_test_day(1, count_lines, count_lines, generate::Options::default(), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "shrunk to:\n")]
+    fn test_differential_fails() {
+        differential_test_day(
+            1,
+            |input| (input.lines().filter(|l| !l.contains('7')).count(), 0),
+            count_lines,
+            generate::Options::default(),
+            5,
+        );
+    }
+
+    fn sum(input: &str) -> i64 {
+        input.lines().map(|l| l.parse::<i64>().unwrap()).sum()
+    }
+
+    fn count(input: &str) -> usize {
+        input.lines().count()
+    }
+
+    #[test]
+    fn test_day_test() {
+        DayTest::new(sum, count)
+            .part1("1\n2\n3", 6)
+            .part2("4\n5", 2)
+            .named("two lines")
+            // Part 1 would fail on this input, but only part 2 is checked.
+            .part2("x", 1)
+            .run();
+    }
+
+    #[test]
+    #[should_panic(expected = "2 of 3 cases failed\n\n\
+                               part 1 example 1:\npart 1 failed:\n- 7\n+ 6\ninput:\n1\n2\n3\n\n\
+                               broken:\npart 1 panicked: \
+                               called `Result::unwrap()` on an `Err` value")]
+    fn test_day_test_fails() {
+        DayTest::new(sum, count)
+            .part1("1\n2\n3", 7)
+            .part2("4\n5", 2)
+            .part1("x", 1)
+            .named("broken")
+            .run();
+    }
+
+    #[test]
+    #[should_panic(expected = "slow:\npart 2 panicked: \
+                               part 2 did not finish within the budget of 50ms")]
+    fn test_day_test_within() {
+        let sleep = |input: &str| thread::sleep(Duration::from_millis(input.parse().unwrap()));
+        let budgets = Budgets {
+            part1: None,
+            part2: Some(Duration::from_millis(50)),
+        };
+
+        DayTest::new(sleep, sleep)
+            .part1("100", ())
+            .part2("0", ())
+            .part2("10000", ())
+            .named("slow")
+            .within(budgets)
+            .run();
+    }
 
-    if let Some(expected2) = expected2 {
-        assert_eq!(answer2, expected2, "Part 2 failed");
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("1", "1"), "  1");
+        assert_eq!(diff("a\nb\nc", "a\nc\nd"), "  a\n- b\n  c\n+ d");
+        assert_eq!(diff("", "x"), "+ x");
     }
 }
@@ -0,0 +1,149 @@
+//! Overflow-safe integer helpers.
+
+/// The number of decimal digits in `n`, where 0 has a single digit.
+pub fn digits(n: u64) -> u32 {
+    n.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// `10^exponent`, or [None] if it does not fit in an `i64`.
+pub fn checked_pow10(exponent: u32) -> Option<i64> {
+    10i64.checked_pow(exponent)
+}
+
+/// Appends the decimal digits of `b` to `a`, so `checked_concat(12, 345) == Some(12345)`.
+/// Returns [None] if `b` is negative or the result overflows.
+pub fn checked_concat(a: i64, b: i64) -> Option<i64> {
+    if b < 0 {
+        return None;
+    }
+
+    a.checked_mul(checked_pow10(digits(b as u64))?)?
+        .checked_add(if a < 0 { -b } else { b })
+}
+
+/// The greatest common divisor. It is unsigned because `gcd(i64::MIN, 0)` is `2^63`, which does
+/// not fit in an `i64`.
+pub fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// The least common multiple, or [None] if it overflows. Always non-negative.
+pub fn checked_lcm(a: i64, b: i64) -> Option<i64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    let gcd = i64::try_from(gcd(a, b)).ok()?;
+    (a / gcd).checked_mul(b).and_then(i64::checked_abs)
+}
+
+/// Returns `(g, x, y)` such that `a * x + b * y == g == gcd(a, b)`.
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1, 0);
+    let (mut old_t, mut t) = (0, 1);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+        (old_t, t) = (t, old_t - q * t);
+    }
+
+    if old_r < 0 {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/// The inverse of `a` modulo `m`, in `0..m`, or [None] if `a` and `m` are not coprime.
+pub fn mod_inverse(a: i64, m: i64) -> Option<i64> {
+    if m <= 0 {
+        return None;
+    }
+
+    let (g, x, _) = extended_gcd(a as i128, m as i128);
+    (g == 1).then(|| x.rem_euclid(m as i128) as i64)
+}
+
+/// Solves the system `x ≡ r (mod m)` for all `(r, m)` pairs using the Chinese remainder theorem.
+/// The moduli need not be coprime. Returns the smallest non-negative solution together with the
+/// combined modulus, or [None] if the system has no solution or the modulus overflows.
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    congruences
+        .iter()
+        .try_fold((0i128, 1i128), |(r1, m1), &(r2, m2)| {
+            if m2 <= 0 {
+                return None;
+            }
+
+            let (r2, m2) = ((r2 as i128).rem_euclid(m2 as i128), m2 as i128);
+            let (g, p, _) = extended_gcd(m1, m2);
+            if (r2 - r1) % g != 0 {
+                return None;
+            }
+
+            let modulus = m1 / g * m2;
+            if modulus > i64::MAX as i128 {
+                return None;
+            }
+
+            let step = ((r2 - r1) / g * p).rem_euclid(m2 / g);
+            Some(((r1 + m1 * step).rem_euclid(modulus), modulus))
+        })
+        .map(|(r, m)| (r as i64, m as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checked_concat, checked_lcm, crt, digits, gcd, mod_inverse};
+
+    #[test]
+    fn test_digits_and_concat() {
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(9), 1);
+        assert_eq!(digits(10), 2);
+        assert_eq!(digits(u64::MAX), 20);
+
+        assert_eq!(checked_concat(12, 345), Some(12345));
+        assert_eq!(checked_concat(168, 0), Some(1680));
+        assert_eq!(checked_concat(-12, 3), Some(-123));
+        assert_eq!(checked_concat(1, -3), None);
+        assert_eq!(checked_concat(i64::MAX / 10, 9), None);
+        assert_eq!(checked_concat(1, 1_000_000_000_000_000_000), None);
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-12, 18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(checked_lcm(4, 6), Some(12));
+        assert_eq!(gcd(i64::MIN, 0), 1 << 63);
+        assert_eq!(gcd(i64::MIN, i64::MIN), 1 << 63);
+        assert_eq!(gcd(i64::MIN, 6), 2);
+
+        assert_eq!(checked_lcm(i64::MAX, 2), None);
+        assert_eq!(checked_lcm(i64::MIN, 2), None);
+        assert_eq!(checked_lcm(i64::MIN, i64::MIN), None);
+        assert_eq!(checked_lcm(i64::MIN, 1), None);
+        assert_eq!(checked_lcm(-4, 6), Some(12));
+    }
+
+    #[test]
+    fn test_mod_inverse_and_crt() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(2, 4), None);
+
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[]), Some((0, 1)));
+    }
+}
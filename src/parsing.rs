@@ -1,9 +1,500 @@
-use std::fmt::Debug;
+pub mod combinators;
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 
-pub fn parse_list<T: FromStr>(input: &str) -> impl Iterator<Item = T> + use<'_, T>
+/// An error produced while parsing puzzle input.
+/// Lines and columns are 1-based; columns are counted in bytes.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+    pub expected: Cow<'static, str>,
+}
+
+impl ParseError {
+    /// Creates an error for `fragment`, which should be a subslice of `source`.
+    /// The column is derived from the position of `fragment` in `source`, and the line is 1.
+    pub fn new(source: &str, fragment: &str, expected: impl Into<Cow<'static, str>>) -> Self {
+        let offset = (fragment.as_ptr() as usize)
+            .checked_sub(source.as_ptr() as usize)
+            .filter(|&o| o <= source.len())
+            .unwrap_or(0);
+        let line = 1 + source[..offset].matches('\n').count();
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            line,
+            column: offset - line_start + 1,
+            text: fragment.to_owned(),
+            expected: expected.into(),
+        }
+    }
+
+    /// Moves the error into a larger input where the parsed text starts at line `first_line`.
+    pub fn relative_to(mut self, first_line: usize) -> Self {
+        self.line += first_line - 1;
+        self
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+
+        if self.text.is_empty() {
+            write!(f, "expected {}, found end of input", self.expected)
+        } else {
+            write!(f, "expected {}, found {:?}", self.expected, self.text)
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Parses every line of the input, panicking with the line number on the first malformed line.
+pub fn parse_list<T>(input: &str) -> impl Iterator<Item = T> + use<'_, T>
+where
+    T: FromStr<Err = ParseError>,
+{
+    try_parse_list(input).map(|r| r.unwrap_or_else(|e| panic!("invalid input: {e}")))
+}
+
+/// Parses every line of the input, reporting errors with their position in the input.
+pub fn try_parse_list<T>(input: &str) -> impl Iterator<Item = Result<T, ParseError>> + use<'_, T>
 where
-    <T as FromStr>::Err: Debug,
+    T: FromStr<Err = ParseError>,
 {
-    input.lines().map(|l| l.parse().unwrap())
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| l.parse().map_err(|e: ParseError| e.relative_to(i + 1)))
+}
+
+/// A block of consecutive non-blank lines from the input.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Section<'a> {
+    /// The 1-based line number of the first line of the section.
+    pub line: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Section<'a> {
+    /// Like [try_parse_list], but with line numbers relative to the whole input.
+    pub fn try_parse_list<T>(self) -> impl Iterator<Item = Result<T, ParseError>> + use<'a, T>
+    where
+        T: FromStr<Err = ParseError>,
+    {
+        let first_line = self.line;
+        try_parse_list(self.text).map(move |r| r.map_err(|e| e.relative_to(first_line)))
+    }
+
+    /// Wraps an error from parsing this section into an error for the whole input.
+    pub fn error(&self, fragment: &str, expected: impl Into<Cow<'static, str>>) -> ParseError {
+        ParseError::new(self.text, fragment, expected).relative_to(self.line)
+    }
+}
+
+/// Splits the input into sections separated by one or more blank lines.
+pub fn sections(input: &str) -> impl Iterator<Item = Section<'_>> {
+    let mut lines = input.lines().enumerate().peekable();
+
+    std::iter::from_fn(move || {
+        while lines.next_if(|(_, l)| l.trim().is_empty()).is_some() {}
+
+        let (first, start) = lines.next()?;
+        let mut end = start;
+        while let Some((_, l)) = lines.next_if(|(_, l)| !l.trim().is_empty()) {
+            end = l;
+        }
+
+        let from = start.as_ptr() as usize - input.as_ptr() as usize;
+        let to = end.as_ptr() as usize - input.as_ptr() as usize + end.len();
+
+        Some(Section {
+            line: first + 1,
+            text: &input[from..to],
+        })
+    })
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Separator<'a> {
+    Whitespace,
+    Delimiter(&'a str),
+}
+
+/// A cursor over the separated fields of a line, for use in [FromStr] implementations.
+/// Errors point at the offending field within the original line.
+#[derive(Debug, Clone)]
+pub struct Fields<'a> {
+    source: &'a str,
+    rest: Option<&'a str>,
+    separator: Separator<'a>,
+}
+
+impl<'a> Fields<'a> {
+    /// Fields separated by any amount of whitespace.
+    pub fn whitespace(s: &'a str) -> Self {
+        Self {
+            source: s,
+            rest: Some(s),
+            separator: Separator::Whitespace,
+        }
+    }
+
+    /// Fields separated by exactly `delimiter`.
+    pub fn delimited(s: &'a str, delimiter: &'a str) -> Self {
+        Self {
+            source: s,
+            rest: Some(s),
+            separator: Separator::Delimiter(delimiter),
+        }
+    }
+
+    /// Splits the remaining fields on whitespace instead.
+    pub fn then_whitespace(self) -> Self {
+        Self {
+            separator: Separator::Whitespace,
+            ..self
+        }
+    }
+
+    /// Splits the remaining fields on `delimiter` instead.
+    pub fn then_delimited(self, delimiter: &'a str) -> Self {
+        Self {
+            separator: Separator::Delimiter(delimiter),
+            ..self
+        }
+    }
+
+    fn end(&self) -> &'a str {
+        &self.source[self.source.len()..]
+    }
+
+    fn next_raw(&mut self) -> Option<&'a str> {
+        match self.separator {
+            Separator::Whitespace => {
+                let rest = self.rest?.trim_start();
+                if rest.is_empty() {
+                    self.rest = None;
+                    return None;
+                }
+
+                let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                self.rest = Some(&rest[len..]);
+                Some(&rest[..len])
+            }
+            Separator::Delimiter(delimiter) => {
+                let rest = self.rest?;
+                if let Some((field, rest)) = rest.split_once(delimiter) {
+                    self.rest = Some(rest);
+                    Some(field)
+                } else {
+                    self.rest = None;
+                    Some(rest)
+                }
+            }
+        }
+    }
+
+    /// Returns the next raw field, failing if there is none.
+    pub fn next_field(
+        &mut self,
+        expected: impl Into<Cow<'static, str>>,
+    ) -> Result<&'a str, ParseError> {
+        self.next_raw()
+            .ok_or_else(|| ParseError::new(self.source, self.end(), expected))
+    }
+
+    /// Parses the next field, failing if there is none or it cannot be parsed.
+    pub fn parse<T: FromStr>(
+        &mut self,
+        expected: impl Into<Cow<'static, str>>,
+    ) -> Result<T, ParseError> {
+        let expected = expected.into();
+        let field = self.next_field(expected.clone())?;
+        field
+            .parse()
+            .map_err(|_| ParseError::new(self.source, field, expected))
+    }
+
+    /// Parses all remaining fields.
+    pub fn parse_rest<T: FromStr>(
+        mut self,
+        expected: impl Into<Cow<'static, str>>,
+    ) -> Result<Vec<T>, ParseError> {
+        let expected = expected.into();
+        let source = self.source;
+        std::iter::from_fn(|| self.next_raw())
+            .map(|f| {
+                f.parse()
+                    .map_err(|_| ParseError::new(source, f, expected.clone()))
+            })
+            .collect()
+    }
+
+    /// Fails if any fields are left.
+    pub fn finish(mut self) -> Result<(), ParseError> {
+        match self.next_raw() {
+            Some(field) => Err(ParseError::new(self.source, field, "end of line")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Integer types that can be extracted with [ints] and [iter_ints].
+pub trait Integer: Copy {
+    /// Whether a `-` directly before a number makes it negative.
+    const SIGNED: bool;
+
+    const ZERO: Self;
+
+    /// Appends a decimal digit, subtracting it instead for negative numbers.
+    fn push_digit(self, digit: u8, negative: bool) -> Option<Self>;
+}
+
+macro_rules! impl_integer {
+    ($signed:literal: $($t:ty),*) => {$(
+        impl Integer for $t {
+            const SIGNED: bool = $signed;
+            const ZERO: Self = 0;
+
+            #[inline]
+            fn push_digit(self, digit: u8, negative: bool) -> Option<Self> {
+                let shifted = self.checked_mul(10)?;
+                if negative {
+                    shifted.checked_sub(digit as Self)
+                } else {
+                    shifted.checked_add(digit as Self)
+                }
+            }
+        }
+    )*};
+}
+
+impl_integer!(false: u8, u16, u32, u64, u128, usize);
+impl_integer!(true: i8, i16, i32, i64, i128, isize);
+
+/// A lazy iterator over the integers in a string, see [try_iter_ints].
+#[derive(Debug, Clone)]
+pub struct TryInts<'a, T> {
+    source: &'a str,
+    position: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Integer> Iterator for TryInts<'_, T> {
+    type Item = Result<T, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.source.as_bytes();
+        let mut i = self.position;
+
+        while i < bytes.len() && !bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+
+        if i == bytes.len() {
+            self.position = i;
+            return None;
+        }
+
+        let start = i;
+        let negative = T::SIGNED && i > 0 && bytes[i - 1] == b'-';
+        let mut value = Some(T::ZERO);
+
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            value = value.and_then(|v| v.push_digit(bytes[i] - b'0', negative));
+            i += 1;
+        }
+
+        self.position = i;
+        Some(value.ok_or_else(|| {
+            let start = if negative { start - 1 } else { start };
+            let expected = format!("an integer that fits in {}", std::any::type_name::<T>());
+            ParseError::new(self.source, &self.source[start..i], expected)
+        }))
+    }
+}
+
+/// Lazily extracts every integer in the string, skipping anything that is not a digit.
+/// For signed types a directly preceding `-` makes the number negative; for unsigned types it is
+/// treated like any other separator. Integers that do not fit the type are errors.
+pub fn try_iter_ints<T: Integer>(s: &str) -> TryInts<'_, T> {
+    TryInts {
+        source: s,
+        position: 0,
+        _marker: std::marker::PhantomData,
+    }
+}
+
+/// Like [try_iter_ints], but panics on integers that do not fit the type.
+pub fn iter_ints<T: Integer>(s: &str) -> impl Iterator<Item = T> + Clone + use<'_, T> {
+    try_iter_ints(s).map(|v| v.unwrap_or_else(|e| panic!("{e}")))
+}
+
+/// Extracts the first `N` integers in the string, failing if there are fewer or if one of them
+/// does not fit the type.
+pub fn try_ints<T: Integer, const N: usize>(s: &str) -> Result<[T; N], ParseError> {
+    let mut iter = try_iter_ints(s);
+    let mut result = [T::ZERO; N];
+
+    for v in result.iter_mut() {
+        *v = iter
+            .next()
+            .unwrap_or_else(|| Err(ParseError::new(s, &s[s.len()..], format!("{N} integers"))))?;
+    }
+
+    Ok(result)
+}
+
+/// Extracts the first `N` integers in the string, so that `let [a, b] = ints(line)` works.
+/// Panics if there are fewer than `N` integers or if one of them does not fit the type.
+pub fn ints<T: Integer, const N: usize>(s: &str) -> [T; N] {
+    try_ints(s).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Splits a `key: values` line on the first colon, trimming both sides.
+pub fn key_values(line: &str) -> Option<(&str, &str)> {
+    let (key, values) = line.split_once(':')?;
+    Some((key.trim(), values.trim()))
+}
+
+/// Splits the input into blocks of lines separated by blank lines, see also [sections].
+pub fn blocks(input: &str) -> impl Iterator<Item = &str> {
+    sections(input).map(|s| s.text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        blocks, ints, iter_ints, key_values, sections, try_ints, try_iter_ints, try_parse_list,
+        Fields, ParseError,
+    };
+    use std::str::FromStr;
+
+    #[derive(Debug, Eq, PartialEq)]
+    struct Pair(u32, u32);
+
+    impl FromStr for Pair {
+        type Err = ParseError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut fields = Fields::delimited(s, ",");
+            let pair = Pair(fields.parse("left number")?, fields.parse("right number")?);
+            fields.finish()?;
+            Ok(pair)
+        }
+    }
+
+    #[test]
+    fn test_try_parse_list() {
+        let parsed = try_parse_list::<Pair>("1,2\n3,4").collect::<Result<Vec<_>, _>>();
+        assert_eq!(parsed, Ok(vec![Pair(1, 2), Pair(3, 4)]));
+
+        let error = try_parse_list::<Pair>("1,2\n3,x4\n5,6")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(error.text, "x4");
+        assert_eq!(error.expected, "right number");
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 3: expected right number, found \"x4\""
+        );
+
+        let error = try_parse_list::<Pair>("1").next().unwrap().unwrap_err();
+        assert_eq!((error.line, error.column), (1, 2));
+        assert_eq!(error.text, "");
+
+        let error = try_parse_list::<Pair>("1,2,3").next().unwrap().unwrap_err();
+        assert_eq!((error.column, error.text.as_str()), (5, "3"));
+    }
+
+    #[test]
+    fn test_fields() {
+        let mut fields = Fields::delimited("7: 1  2 3", ": ");
+        assert_eq!(fields.parse::<u32>("target"), Ok(7));
+        assert_eq!(
+            fields.then_whitespace().parse_rest::<u32>("number"),
+            Ok(vec![1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let input = "1,2\n3,4\n\n\n5,6\n\n7,a\n";
+        let sections = sections(input).collect::<Vec<_>>();
+
+        assert_eq!(sections.len(), 3);
+        assert_eq!((sections[0].line, sections[0].text), (1, "1,2\n3,4"));
+        assert_eq!((sections[1].line, sections[1].text), (5, "5,6"));
+        assert_eq!((sections[2].line, sections[2].text), (7, "7,a"));
+
+        let error = sections[2]
+            .try_parse_list::<Pair>()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!((error.line, error.column), (7, 3));
+    }
+
+    #[test]
+    fn test_ints() {
+        let [a, b] = ints("3   4");
+        assert_eq!((a, b), (3usize, 4));
+
+        assert_eq!(ints::<i64, 3>("x=-12, y=5-7"), [-12, 5, -7]);
+        assert_eq!(ints::<u64, 3>("x=-12, y=5-7"), [12, 5, 7]);
+        assert_eq!(
+            try_ints::<u8, 3>("1 2").unwrap_err().to_string(),
+            "line 1, column 4: expected 3 integers, found end of input"
+        );
+        assert_eq!(ints::<i8, 2>("-128 127"), [-128, 127]);
+
+        assert_eq!(
+            iter_ints::<u32>("75,47,61\n97,61").collect::<Vec<_>>(),
+            [75, 47, 61, 97, 61]
+        );
+        assert_eq!(iter_ints::<u32>("no numbers").next(), None);
+    }
+
+    #[test]
+    fn test_ints_overflow() {
+        let results = try_iter_ints::<i8>("1 -129 300 4\n-128").collect::<Vec<_>>();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0], Ok(1));
+        assert_eq!(results[3], Ok(4));
+        assert_eq!(results[4], Ok(-128));
+
+        let error = results[1].clone().unwrap_err();
+        assert_eq!(
+            (error.line, error.column, error.text.as_str()),
+            (1, 3, "-129")
+        );
+        assert_eq!(error.expected, "an integer that fits in i8");
+        assert_eq!(results[2].clone().unwrap_err().text, "300");
+
+        assert!(try_ints::<u8, 2>("1 256").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected an integer that fits in u8, found \"256\"")]
+    fn test_iter_ints_panics_on_overflow() {
+        iter_ints::<u8>("256").for_each(drop);
+    }
+
+    #[test]
+    fn test_key_values_and_blocks() {
+        assert_eq!(key_values("190: 10 19"), Some(("190", "10 19")));
+        assert_eq!(key_values("190 10 19"), None);
+        assert_eq!(
+            blocks("1|2\n3|4\n\n1,2\n").collect::<Vec<_>>(),
+            ["1|2\n3|4", "1,2"]
+        );
+    }
 }
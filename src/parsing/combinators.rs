@@ -0,0 +1,350 @@
+//! Small zero-copy parser combinators for instruction-like inputs.
+//!
+//! Parsers work on byte positions within the complete input, so every [Match] knows where in the
+//! input it was found.
+
+use crate::parsing::{Integer, ParseError};
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+pub trait Parser<'a> {
+    type Output;
+
+    /// Tries to parse at byte `position` of `input`, returning the output and the position after it.
+    fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)>;
+
+    /// Transforms the output of the parser.
+    fn map<F, T>(self, f: F) -> Map<Self, F>
+    where
+        Self: Sized,
+        F: Fn(Self::Output) -> T,
+    {
+        Map(self, f)
+    }
+
+    /// Replaces the output of the parser with a fixed value.
+    fn to<T: Clone>(self, value: T) -> To<Self, T>
+    where
+        Self: Sized,
+    {
+        To(self, value)
+    }
+
+    /// Finds the first match at or after byte `from`.
+    fn find(&self, input: &'a str, from: usize) -> Option<Match<Self::Output>> {
+        (from..=input.len()).find_map(|start| {
+            self.parse_at(input, start)
+                .map(|(value, end)| Match { value, start, end })
+        })
+    }
+}
+
+impl<'a, P: Parser<'a>> Parser<'a> for &P {
+    type Output = P::Output;
+
+    fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+        (*self).parse_at(input, position)
+    }
+}
+
+/// A parsed value together with the byte range it was parsed from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Match<T> {
+    pub value: T,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Literal(&'static str);
+
+/// Matches exactly `text`, returning the matched slice.
+pub fn literal(text: &'static str) -> Literal {
+    Literal(text)
+}
+
+impl<'a> Parser<'a> for Literal {
+    type Output = &'a str;
+
+    fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+        let end = position + self.0.len();
+        input
+            .as_bytes()
+            .get(position..end)
+            .filter(|b| *b == self.0.as_bytes())
+            .map(|_| (&input[position..end], end))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Number<T> {
+    min_digits: usize,
+    max_digits: usize,
+    _marker: PhantomData<T>,
+}
+
+/// Matches an unsigned decimal number of `min_digits..=max_digits` digits.
+/// Digits beyond `max_digits` are left for the next parser, and numbers that overflow `T` fail.
+pub fn number<T: Integer>(min_digits: usize, max_digits: usize) -> Number<T> {
+    Number {
+        min_digits,
+        max_digits,
+        _marker: PhantomData,
+    }
+}
+
+impl<T: Integer> Parser<'_> for Number<T> {
+    type Output = T;
+
+    fn parse_at(&self, input: &str, position: usize) -> Option<(Self::Output, usize)> {
+        let digits = input
+            .as_bytes()
+            .get(position..)?
+            .iter()
+            .take(self.max_digits)
+            .take_while(|b| b.is_ascii_digit());
+
+        let mut value = T::ZERO;
+        let mut count = 0;
+        for d in digits {
+            value = value.push_digit(d - b'0', false)?;
+            count += 1;
+        }
+
+        (count >= self.min_digits).then_some((value, position + count))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Seq<T>(T);
+
+/// Matches all parsers in the tuple one after another, returning a tuple of their outputs.
+pub fn seq<T>(parsers: T) -> Seq<T> {
+    Seq(parsers)
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Alt<T>(T);
+
+/// Tries the parsers in the tuple in order, returning the output of the first that matches.
+pub fn alt<T>(parsers: T) -> Alt<T> {
+    Alt(parsers)
+}
+
+macro_rules! impl_tuple_parsers {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<'a, $first: Parser<'a>, $($rest: Parser<'a>),*> Parser<'a> for Seq<($first, $($rest),*)> {
+            type Output = ($first::Output, $($rest::Output),*);
+
+            #[allow(non_snake_case)]
+            fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+                let ($first, $($rest),*) = &self.0;
+                let ($first, position) = $first.parse_at(input, position)?;
+                $(let ($rest, position) = $rest.parse_at(input, position)?;)*
+                Some((($first, $($rest),*), position))
+            }
+        }
+
+        impl<'a, $first: Parser<'a>, $($rest: Parser<'a, Output = $first::Output>),*> Parser<'a>
+            for Alt<($first, $($rest),*)>
+        {
+            type Output = $first::Output;
+
+            #[allow(non_snake_case)]
+            fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+                let ($first, $($rest),*) = &self.0;
+                $first.parse_at(input, position)
+                    $(.or_else(|| $rest.parse_at(input, position)))*
+            }
+        }
+    };
+}
+
+impl_tuple_parsers!(A, B);
+impl_tuple_parsers!(A, B, C);
+impl_tuple_parsers!(A, B, C, D);
+impl_tuple_parsers!(A, B, C, D, E);
+impl_tuple_parsers!(A, B, C, D, E, F);
+impl_tuple_parsers!(A, B, C, D, E, F, G);
+impl_tuple_parsers!(A, B, C, D, E, F, G, H);
+
+#[derive(Debug, Copy, Clone)]
+pub struct Repeat<P> {
+    parser: P,
+    min: usize,
+    max: usize,
+}
+
+/// Matches `parser` between `min` and `max` times, returning all outputs.
+/// Stops early if the parser matches without consuming anything.
+pub fn repeat<P>(parser: P, min: usize, max: usize) -> Repeat<P> {
+    Repeat { parser, min, max }
+}
+
+/// Matches `parser` any number of times, including zero.
+pub fn many<P>(parser: P) -> Repeat<P> {
+    repeat(parser, 0, usize::MAX)
+}
+
+impl<'a, P: Parser<'a>> Parser<'a> for Repeat<P> {
+    type Output = Vec<P::Output>;
+
+    fn parse_at(&self, input: &'a str, mut position: usize) -> Option<(Self::Output, usize)> {
+        let mut result = vec![];
+
+        while result.len() < self.max {
+            match self.parser.parse_at(input, position) {
+                Some((value, next)) => {
+                    result.push(value);
+                    if next == position {
+                        break;
+                    }
+                    position = next;
+                }
+                None => break,
+            }
+        }
+
+        (result.len() >= self.min).then_some((result, position))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Optional<P>(P);
+
+/// Matches `parser` if possible, never failing.
+pub fn opt<P>(parser: P) -> Optional<P> {
+    Optional(parser)
+}
+
+impl<'a, P: Parser<'a>> Parser<'a> for Optional<P> {
+    type Output = Option<P::Output>;
+
+    fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+        Some(match self.0.parse_at(input, position) {
+            Some((value, next)) => (Some(value), next),
+            None => (None, position),
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Map<P, F>(P, F);
+
+impl<'a, P: Parser<'a>, F: Fn(P::Output) -> T, T> Parser<'a> for Map<P, F> {
+    type Output = T;
+
+    fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+        self.0
+            .parse_at(input, position)
+            .map(|(value, next)| ((self.1)(value), next))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct To<P, T>(P, T);
+
+impl<'a, P: Parser<'a>, T: Clone> Parser<'a> for To<P, T> {
+    type Output = T;
+
+    fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+        self.0
+            .parse_at(input, position)
+            .map(|(_, next)| (self.1.clone(), next))
+    }
+}
+
+/// Scans the input for non-overlapping matches, skipping any bytes where the parser fails.
+pub fn scan<'a, P: Parser<'a>>(
+    parser: P,
+    input: &'a str,
+) -> impl Iterator<Item = Match<P::Output>> + use<'a, P> {
+    let mut position = 0;
+
+    std::iter::from_fn(move || {
+        let m = parser.find(input, position)?;
+        position = m.end.max(m.start + 1);
+        Some(m)
+    })
+}
+
+/// Parses the entire input, failing with the position where parsing stopped.
+pub fn parse_complete<'a, P: Parser<'a>>(
+    parser: P,
+    input: &'a str,
+    expected: impl Into<Cow<'static, str>>,
+) -> Result<P::Output, ParseError> {
+    match parser.parse_at(input, 0) {
+        Some((value, end)) if end == input.len() => Ok(value),
+        Some((_, end)) => Err(ParseError::new(input, &input[end..], expected)),
+        None => Err(ParseError::new(input, input, expected)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{alt, literal, many, number, opt, parse_complete, scan, seq, Match, Parser};
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number::<u32>(1, 3).parse_at("12345", 0), Some((123, 3)));
+        assert_eq!(number::<u32>(1, 3).parse_at("x1", 1), Some((1, 2)));
+        assert_eq!(number::<u32>(2, 3).parse_at("1,", 0), None);
+        assert_eq!(number::<u8>(1, 5).parse_at("256", 0), None);
+        assert_eq!(number::<u8>(1, 5).parse_at("", 0), None);
+    }
+
+    #[test]
+    fn test_seq_alt() {
+        let pair =
+            seq((number::<u32>(1, 3), literal(","), number::<u32>(1, 3))).map(|(a, _, b)| a + b);
+        assert_eq!(pair.parse_at("12,30", 0), Some((42, 5)));
+        assert_eq!(pair.parse_at("12;30", 0), None);
+
+        let keyword = alt((literal("do").to(true), literal("don't").to(false)));
+        assert_eq!(keyword.parse_at("don't", 0), Some((true, 2)));
+        assert_eq!(keyword.parse_at("xdon't", 1), Some((true, 3)));
+        assert_eq!(keyword.parse_at("x", 0), None);
+    }
+
+    #[test]
+    fn test_many_opt() {
+        let list = seq((
+            number::<u32>(1, 9),
+            many(seq((literal(","), number::<u32>(1, 9)))),
+        ))
+        .map(|(first, rest)| {
+            [first]
+                .into_iter()
+                .chain(rest.into_iter().map(|(_, v)| v))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(parse_complete(&list, "1,2,3", "list"), Ok(vec![1, 2, 3]));
+
+        let error = parse_complete(&list, "1,2;3", "list").unwrap_err();
+        assert_eq!((error.column, error.text.as_str()), (4, ";3"));
+
+        assert_eq!(opt(literal("-")).parse_at("5", 0), Some((None, 0)));
+    }
+
+    #[test]
+    fn test_scan() {
+        let input = "a12bb3c";
+        assert_eq!(
+            scan(number::<u32>(1, 9), input).collect::<Vec<_>>(),
+            [
+                Match {
+                    value: 12,
+                    start: 1,
+                    end: 3
+                },
+                Match {
+                    value: 3,
+                    start: 5,
+                    end: 6
+                }
+            ]
+        );
+        assert_eq!(number::<u32>(1, 9).find(input, 3).map(|m| m.start), Some(5));
+    }
+}
@@ -0,0 +1,109 @@
+//! A small seedable pseudo random number generator (SplitMix64).
+//!
+//! Sequences only depend on the seed, so generated inputs are reproducible across runs and machines.
+
+use std::ops::{Bound, RangeBounds};
+
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+/// Integer types that [Rng::range] can sample.
+pub trait Sample: Copy {
+    fn to_i128(self) -> i128;
+
+    fn from_i128(v: i128) -> Self;
+}
+
+macro_rules! impl_sample {
+    ($($t:ty),*) => {$(
+        impl Sample for $t {
+            fn to_i128(self) -> i128 {
+                self as i128
+            }
+
+            fn from_i128(v: i128) -> Self {
+                v as Self
+            }
+        }
+    )*};
+}
+
+impl_sample!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed value below `n`, which must be positive.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "cannot sample from an empty range");
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// A uniformly distributed value in the range, which must not be empty.
+    pub fn range<T: Sample>(&mut self, range: impl RangeBounds<T>) -> T {
+        let low = match range.start_bound() {
+            Bound::Included(v) => v.to_i128(),
+            Bound::Excluded(v) => v.to_i128() + 1,
+            Bound::Unbounded => panic!("range should have a lower bound"),
+        };
+        let high = match range.end_bound() {
+            Bound::Included(v) => v.to_i128() + 1,
+            Bound::Excluded(v) => v.to_i128(),
+            Bound::Unbounded => panic!("range should have an upper bound"),
+        };
+        assert!(low < high, "cannot sample from an empty range");
+
+        let offset = match u64::try_from(high - low) {
+            Ok(span) => self.below(span),
+            // The range covers every value of a 64-bit type.
+            Err(_) => self.next_u64(),
+        };
+        T::from_i128(low + offset as i128)
+    }
+
+    /// Returns true with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    pub fn choose<'a, T>(&mut self, values: &'a [T]) -> &'a T {
+        &values[self.range(0..values.len())]
+    }
+
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            values.swap(i, self.range(0..=i));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_range() {
+        let mut rng = Rng::new(42);
+
+        for _ in 0..1000 {
+            assert!((3..7).contains(&rng.range(3..7)));
+            assert!((-5..=5).contains(&rng.range(-5i64..=5)));
+        }
+
+        assert_eq!(rng.range(9u8..=9), 9);
+        rng.range(i64::MIN..=i64::MAX);
+        assert_eq!(Rng::new(7).next_u64(), Rng::new(7).next_u64());
+    }
+}
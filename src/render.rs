@@ -0,0 +1,215 @@
+//! Drawing of grids as plain or coloured text, PNG images and GIF animations.
+//!
+//! Anything implementing [Grid] can be drawn; every cell is drawn as a character in text and as a
+//! square of a single colour in images.
+
+use std::io::{self, Write};
+
+pub type Rgb = [u8; 3];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cell {
+    pub symbol: char,
+    pub color: Rgb,
+}
+
+impl Cell {
+    pub const fn new(symbol: char, color: Rgb) -> Self {
+        Self { symbol, color }
+    }
+}
+
+pub trait Grid {
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    fn cell(&self, row: usize, column: usize) -> Cell;
+}
+
+/// Draws the grid as text, one line per row.
+pub fn to_ascii(grid: &impl Grid) -> String {
+    let mut result = String::with_capacity((grid.width() + 1) * grid.height());
+
+    for r in 0..grid.height() {
+        result.extend((0..grid.width()).map(|c| grid.cell(r, c).symbol));
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Draws the grid as text coloured with ANSI escape codes, for terminals supporting 24-bit colour.
+pub fn to_ansi(grid: &impl Grid) -> String {
+    let mut result = String::new();
+
+    for r in 0..grid.height() {
+        let mut current = None;
+        for c in 0..grid.width() {
+            let cell = grid.cell(r, c);
+            if current != Some(cell.color) {
+                let [red, green, blue] = cell.color;
+                result += &format!("\x1b[38;2;{red};{green};{blue}m");
+                current = Some(cell.color);
+            }
+            result.push(cell.symbol);
+        }
+        result += "\x1b[0m\n";
+    }
+
+    result
+}
+
+/// Draws the grid as RGB pixels, with every cell a `scale` by `scale` square.
+fn to_pixels(grid: &impl Grid, scale: usize) -> Vec<u8> {
+    let row_length = grid.width() * scale * 3;
+    let mut pixels = Vec::with_capacity(row_length * grid.height() * scale);
+
+    for r in 0..grid.height() {
+        let start = pixels.len();
+        for c in 0..grid.width() {
+            let color = grid.cell(r, c).color;
+            for _ in 0..scale {
+                pixels.extend_from_slice(&color);
+            }
+        }
+        for _ in 1..scale {
+            pixels.extend_from_within(start..start + row_length);
+        }
+    }
+
+    pixels
+}
+
+fn image_size(grid: &impl Grid, scale: usize) -> io::Result<(u16, u16)> {
+    let size = |cells: usize| {
+        cells
+            .checked_mul(scale)
+            .and_then(|v| u16::try_from(v).ok())
+            .filter(|&v| v > 0)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unsupported image size"))
+    };
+
+    Ok((size(grid.width())?, size(grid.height())?))
+}
+
+/// Writes the grid as a PNG image, with every cell a `scale` by `scale` square.
+pub fn write_png(grid: &impl Grid, scale: usize, writer: impl Write) -> io::Result<()> {
+    let (width, height) = image_size(grid, scale)?;
+
+    let mut encoder = png::Encoder::new(writer, width.into(), height.into());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    encoder
+        .write_header()
+        .and_then(|mut w| w.write_image_data(&to_pixels(grid, scale)))
+        .map_err(io::Error::other)
+}
+
+/// A looping GIF animation of a grid changing over time.
+pub struct Animation<W: Write> {
+    encoder: gif::Encoder<W>,
+    size: (u16, u16),
+    scale: usize,
+    delay: u16,
+}
+
+impl<W: Write> Animation<W> {
+    /// Starts an animation of grids the size of `grid`, showing every frame for `delay` hundredths
+    /// of a second.
+    pub fn new(writer: W, grid: &impl Grid, scale: usize, delay: u16) -> io::Result<Self> {
+        let size = image_size(grid, scale)?;
+        let mut encoder =
+            gif::Encoder::new(writer, size.0, size.1, &[]).map_err(io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+
+        Ok(Self {
+            encoder,
+            size,
+            scale,
+            delay,
+        })
+    }
+
+    /// Adds a frame, which must have the same size as the grid the animation was started with.
+    pub fn add_frame(&mut self, grid: &impl Grid) -> io::Result<()> {
+        if image_size(grid, self.scale)? != self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame size differs from animation size",
+            ));
+        }
+
+        let mut frame =
+            gif::Frame::from_rgb_speed(self.size.0, self.size.1, &to_pixels(grid, self.scale), 10);
+        frame.delay = self.delay;
+        self.encoder.write_frame(&frame).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_ansi, to_ascii, write_png, Animation, Cell, Grid};
+
+    struct Checkerboard(usize);
+
+    impl Grid for Checkerboard {
+        fn width(&self) -> usize {
+            self.0
+        }
+
+        fn height(&self) -> usize {
+            2
+        }
+
+        fn cell(&self, row: usize, column: usize) -> Cell {
+            if (row + column) % 2 == 0 {
+                Cell::new('#', [0, 0, 0])
+            } else {
+                Cell::new('.', [255, 255, 255])
+            }
+        }
+    }
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!(to_ascii(&Checkerboard(3)), "#.#\n.#.\n");
+    }
+
+    #[test]
+    fn test_ansi() {
+        assert_eq!(
+            to_ansi(&Checkerboard(2)),
+            "\x1b[38;2;0;0;0m#\x1b[38;2;255;255;255m.\x1b[0m\n\
+             \x1b[38;2;255;255;255m.\x1b[38;2;0;0;0m#\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_png() {
+        let mut data = vec![];
+        write_png(&Checkerboard(3), 2, &mut data).unwrap();
+
+        let decoder = png::Decoder::new(data.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+
+        assert_eq!((info.width, info.height), (6, 4));
+        assert_eq!(&pixels[..9], [0, 0, 0, 0, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_animation() {
+        let mut data = vec![];
+        let mut animation = Animation::new(&mut data, &Checkerboard(3), 2, 10).unwrap();
+        animation.add_frame(&Checkerboard(3)).unwrap();
+        assert!(animation.add_frame(&Checkerboard(4)).is_err());
+        drop(animation);
+
+        assert!(data.starts_with(b"GIF89a"));
+    }
+}
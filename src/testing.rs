@@ -0,0 +1,224 @@
+//! Property-based testing on top of [Rng].
+//!
+//! A [Strategy] generates random values, and [check] asserts that a property holds for many of
+//! them. Values come from a fixed seed and failures report the generated value, so they can be
+//! reproduced exactly.
+
+use crate::random::{Rng, Sample};
+use std::fmt::{Debug, Display};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+pub trait Strategy {
+    type Value: Debug;
+
+    fn generate(&self, rng: &mut Rng) -> Self::Value;
+}
+
+macro_rules! impl_tuple_strategies {
+    ($($s:ident),*) => {
+        impl<$($s: Strategy),*> Strategy for ($($s,)*) {
+            type Value = ($($s::Value,)*);
+
+            #[allow(non_snake_case)]
+            fn generate(&self, rng: &mut Rng) -> Self::Value {
+                let ($($s,)*) = self;
+                ($($s.generate(rng),)*)
+            }
+        }
+    };
+}
+
+impl_tuple_strategies!(A, B);
+impl_tuple_strategies!(A, B, C);
+impl_tuple_strategies!(A, B, C, D);
+
+pub struct FromFn<F>(F);
+
+/// A strategy generating values with a function.
+pub fn from_fn<T: Debug, F: Fn(&mut Rng) -> T>(f: F) -> FromFn<F> {
+    FromFn(f)
+}
+
+impl<T: Debug, F: Fn(&mut Rng) -> T> Strategy for FromFn<F> {
+    type Value = T;
+
+    fn generate(&self, rng: &mut Rng) -> T {
+        (self.0)(rng)
+    }
+}
+
+/// Integers in the range, with the bounds and small values more likely than elsewhere, since
+/// that is where most bugs hide.
+pub fn ints<T: Sample + Debug>(range: RangeInclusive<T>) -> impl Strategy<Value = T> {
+    from_fn(move |rng| {
+        let (low, high) = (*range.start(), *range.end());
+        match rng.range(0..10) {
+            0 => low,
+            1 => high,
+            2 => T::from_i128((low.to_i128() + rng.range(0..=16i64) as i128).min(high.to_i128())),
+            _ => rng.range(low..=high),
+        }
+    })
+}
+
+/// Vectors of values from `element` with a length in `length`.
+pub fn vecs<S: Strategy>(
+    element: S,
+    length: RangeInclusive<usize>,
+) -> impl Strategy<Value = Vec<S::Value>> {
+    from_fn(move |rng| {
+        (0..rng.range(length.clone()))
+            .map(|_| element.generate(rng))
+            .collect()
+    })
+}
+
+/// Like [vecs], but sorted in ascending order.
+pub fn sorted_vecs<S: Strategy>(
+    element: S,
+    length: RangeInclusive<usize>,
+) -> impl Strategy<Value = Vec<S::Value>>
+where
+    S::Value: Ord,
+{
+    let vecs = vecs(element, length);
+    from_fn(move |rng| {
+        let mut values = vecs.generate(rng);
+        values.sort();
+        values
+    })
+}
+
+/// Rectangular grids of letters from `alphabet`, as text with one line per row.
+pub fn grids(
+    width: RangeInclusive<usize>,
+    height: RangeInclusive<usize>,
+    alphabet: &'static [u8],
+) -> impl Strategy<Value = String> {
+    from_fn(move |rng| {
+        let width = rng.range(width.clone());
+        (0..rng.range(height.clone()))
+            .map(|_| {
+                (0..width)
+                    .map(|_| char::from(*rng.choose(alphabet)))
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    })
+}
+
+/// Inputs of one record per line, together with the records they were formatted from.
+pub fn records<S: Strategy>(
+    record: S,
+    count: RangeInclusive<usize>,
+) -> impl Strategy<Value = (String, Vec<S::Value>)>
+where
+    S::Value: Display,
+{
+    let records = vecs(record, count);
+    from_fn(move |rng| {
+        let records = records.generate(rng);
+        let input = records.iter().map(|r| format!("{r}\n")).collect();
+        (input, records)
+    })
+}
+
+/// Asserts that `property` holds for `cases` values generated from a fixed seed.
+pub fn check<S: Strategy>(cases: usize, strategy: S, property: impl Fn(&S::Value) -> bool) {
+    let mut rng = Rng::new(0);
+
+    for case in 0..cases {
+        let value = strategy.generate(&mut rng);
+        assert!(
+            property(&value),
+            "property failed in case {case} for {value:?}"
+        );
+    }
+}
+
+/// Asserts that `property` returns the same result as `reference` for every generated value.
+pub fn check_against<S: Strategy, T: PartialEq + Debug>(
+    cases: usize,
+    strategy: S,
+    property: impl Fn(&S::Value) -> T,
+    reference: impl Fn(&S::Value) -> T,
+) {
+    let mut rng = Rng::new(0);
+
+    for case in 0..cases {
+        let value = strategy.generate(&mut rng);
+        assert_eq!(
+            property(&value),
+            reference(&value),
+            "result differs from reference in case {case} for {value:?}"
+        );
+    }
+}
+
+/// Whether formatting the value and parsing it again gives back the same value.
+pub fn round_trips<T>(value: &T) -> bool
+where
+    T: Display + FromStr + PartialEq,
+{
+    value.to_string().parse::<T>().is_ok_and(|v| v == *value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check, check_against, grids, ints, records, round_trips, sorted_vecs, vecs, Strategy,
+    };
+    use crate::parsing;
+    use crate::random::Rng;
+
+    #[test]
+    fn test_ints() {
+        check(1000, ints(-5i64..=5), |v| (-5..=5).contains(v));
+
+        let mut rng = Rng::new(1);
+        let strategy = ints(0u64..=u64::MAX);
+        let values = (0..100)
+            .map(|_| strategy.generate(&mut rng))
+            .collect::<Vec<_>>();
+        assert!(values.contains(&0) && values.contains(&u64::MAX));
+    }
+
+    #[test]
+    fn test_collections() {
+        check(200, vecs(ints(0u8..=9), 2..=4), |v| {
+            (2..=4).contains(&v.len())
+        });
+        check(200, sorted_vecs(ints(0u8..=9), 0..=20), |v| v.is_sorted());
+        check(200, grids(1..=5, 0..=5, b"#."), |grid| {
+            let widths = grid.lines().map(str::len).collect::<Vec<_>>();
+            widths.windows(2).all(|w| w[0] == w[1]) && grid.chars().all(|c| "#.\n".contains(c))
+        });
+    }
+
+    #[test]
+    fn test_records() {
+        check_against(
+            200,
+            records(ints(0u32..=99), 0..=10),
+            |(input, _)| parsing::iter_ints::<u32>(input).collect::<Vec<_>>(),
+            |(_, records)| records.clone(),
+        );
+    }
+
+    #[test]
+    fn test_round_trips() {
+        check(500, ints(i64::MIN..=i64::MAX), round_trips);
+        check(200, (ints(0u8..=1), ints(0u8..=1)), |&(a, b)| {
+            round_trips(&(a == b))
+        });
+        assert!(!round_trips(&f64::NAN));
+    }
+
+    #[test]
+    #[should_panic(expected = "property failed in case")]
+    fn test_check_fails() {
+        check(100, ints(0u8..=9), |&v| v < 9);
+    }
+}
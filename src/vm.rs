@@ -0,0 +1,190 @@
+//! A small virtual machine for programs hidden in corrupted memory.
+//!
+//! Every opcode is registered with a parser for its syntax and a function for its semantics.
+//! Running a program scans the memory for any registered instruction, skipping everything else,
+//! and executes the instructions in the order they were found.
+
+use crate::parsing::combinators::{scan, Parser};
+use std::collections::BTreeMap;
+
+/// The operands of a decoded instruction.
+pub type Operands = Vec<i64>;
+
+/// The semantics of an opcode, applied to the state with the operands of the instruction.
+pub type Execute = fn(&mut State, &[i64]);
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct State {
+    /// Whether instructions that respect it should take effect. Starts enabled.
+    pub enabled: bool,
+    pub accumulator: i64,
+    pub registers: BTreeMap<&'static str, i64>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            accumulator: 0,
+            registers: BTreeMap::new(),
+        }
+    }
+}
+
+impl State {
+    /// The value of a register, which is zero until it is first written.
+    pub fn register(&self, name: &'static str) -> i64 {
+        self.registers.get(name).copied().unwrap_or(0)
+    }
+
+    pub fn register_mut(&mut self, name: &'static str) -> &mut i64 {
+        self.registers.entry(name).or_insert(0)
+    }
+}
+
+struct Opcode<'a> {
+    name: &'static str,
+    syntax: Box<dyn Parser<'a, Output = Operands> + 'a>,
+    execute: Execute,
+}
+
+/// An executed instruction and the state after executing it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Step {
+    pub opcode: &'static str,
+    pub operands: Operands,
+    /// The byte range of the instruction in the memory.
+    pub start: usize,
+    pub end: usize,
+    pub state: State,
+}
+
+#[derive(Default)]
+pub struct Machine<'a> {
+    opcodes: Vec<Opcode<'a>>,
+}
+
+impl<'a> Machine<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an opcode. Where several opcodes match at the same position, the first registered
+    /// one is used.
+    pub fn opcode(
+        mut self,
+        name: &'static str,
+        syntax: impl Parser<'a, Output = Operands> + 'a,
+        execute: Execute,
+    ) -> Self {
+        self.opcodes.push(Opcode {
+            name,
+            syntax: Box::new(syntax),
+            execute,
+        });
+        self
+    }
+
+    /// Runs the program in `memory` from the initial state, returning the final state.
+    pub fn run(&self, memory: &'a str) -> State {
+        let mut state = State::default();
+        for (opcode, operands, _, _) in self.decode(memory) {
+            (opcode.execute)(&mut state, &operands);
+        }
+        state
+    }
+
+    /// Runs the program in `memory` from the initial state, returning every executed step.
+    pub fn trace(&self, memory: &'a str) -> Vec<Step> {
+        let mut state = State::default();
+        self.decode(memory)
+            .map(|(opcode, operands, start, end)| {
+                (opcode.execute)(&mut state, &operands);
+                Step {
+                    opcode: opcode.name,
+                    operands,
+                    start,
+                    end,
+                    state: state.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn decode(
+        &self,
+        memory: &'a str,
+    ) -> impl Iterator<Item = (&Opcode<'a>, Operands, usize, usize)> + '_ {
+        scan(AnyOpcode(&self.opcodes), memory).map(|m| {
+            let (index, operands) = m.value;
+            (&self.opcodes[index], operands, m.start, m.end)
+        })
+    }
+}
+
+/// Matches the first registered opcode, returning its index and operands.
+struct AnyOpcode<'m, 'a>(&'m [Opcode<'a>]);
+
+impl<'a> Parser<'a> for AnyOpcode<'_, 'a> {
+    type Output = (usize, Operands);
+
+    fn parse_at(&self, input: &'a str, position: usize) -> Option<(Self::Output, usize)> {
+        self.0.iter().enumerate().find_map(|(index, opcode)| {
+            opcode
+                .syntax
+                .parse_at(input, position)
+                .map(|(operands, end)| ((index, operands), end))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Machine;
+    use crate::parsing::combinators::{literal, number, seq, Parser};
+
+    fn machine<'a>() -> Machine<'a> {
+        Machine::new()
+            .opcode(
+                "add",
+                seq((literal("add "), number(1, 9))).map(|(_, v)| vec![v]),
+                |state, operands| state.accumulator += operands[0],
+            )
+            .opcode("count", literal("add").map(|_| vec![]), |state, _| {
+                *state.register_mut("count") += 1
+            })
+            .opcode("off", literal("off").map(|_| vec![]), |state, _| {
+                state.enabled = false
+            })
+    }
+
+    #[test]
+    fn test_run() {
+        let state = machine().run("xadd 12 add add 30?off");
+        assert_eq!(state.accumulator, 42);
+        assert_eq!(state.register("count"), 1);
+        assert_eq!(state.register("other"), 0);
+        assert!(!state.enabled);
+    }
+
+    #[test]
+    fn test_trace() {
+        let trace = machine().trace("xadd 12 add!");
+        let steps = trace
+            .iter()
+            .map(|s| {
+                (
+                    s.opcode,
+                    s.operands.as_slice(),
+                    s.start,
+                    s.end,
+                    s.state.accumulator,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [("add", &[12][..], 1, 7, 12), ("count", &[][..], 8, 11, 12)]
+        );
+    }
+}